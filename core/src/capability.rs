@@ -0,0 +1,213 @@
+//! What each model of X52 can do
+
+use crate::{
+	X52DeviceType, X52VendorCommand, X52_PRO_REPORT_LEN, X52_REPORT_LEN,
+};
+use core::fmt::{Display, Formatter};
+
+/// A feature of the X52 which not every model has to have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52Capability {
+	/// Turning the red and green halves of LEDs on and off by ID, with
+	/// [`X52VendorCommand::SetLed`]
+	IndividualLeds,
+	/// Setting the color of the colored LEDs
+	ColoredLeds,
+	/// Turning the fire button and throttle LEDs on and off
+	OnOffLeds,
+	/// Setting the brightness of the LEDs
+	LedBrightness,
+	/// Writing text to the MFD and setting its brightness
+	Mfd,
+	/// Setting the clocks and date on the MFD
+	Clocks,
+	/// Blinking the throttle and POV hat LEDs
+	Blink,
+	/// Showing the shift indicator on the MFD
+	Shift,
+}
+
+impl X52Capability {
+	/// Every capability
+	pub const ALL: [X52Capability; 8] = [
+		Self::IndividualLeds,
+		Self::ColoredLeds,
+		Self::OnOffLeds,
+		Self::LedBrightness,
+		Self::Mfd,
+		Self::Clocks,
+		Self::Blink,
+		Self::Shift,
+	];
+
+	/// The bit of the capability in [`X52Capabilities`]
+	const fn bit(&self) -> u16 {
+		1 << *self as u16
+	}
+}
+
+impl Display for X52Capability {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> core::fmt::Result {
+		fmt.write_str(match self {
+			Self::IndividualLeds => "individual LEDs",
+			Self::ColoredLeds => "colored LEDs",
+			Self::OnOffLeds => "on/off LEDs",
+			Self::LedBrightness => "LED brightness",
+			Self::Mfd => "the MFD",
+			Self::Clocks => "clocks",
+			Self::Blink => "blinking",
+			Self::Shift => "the shift indicator",
+		})
+	}
+}
+
+/// The layout of the input reports a model of X52 sends, see
+/// [`X52InputReport`](crate::X52InputReport)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct X52ReportFormat {
+	len: usize,
+	buttons: u8,
+	stick_bits: u8,
+}
+
+impl X52ReportFormat {
+	/// The length of a report in bytes
+	pub const fn report_len(&self) -> usize {
+		self.len
+	}
+
+	/// The number of buttons in a report
+	pub const fn buttons(&self) -> u8 {
+		self.buttons
+	}
+
+	/// The number of bits of the X and Y axes of the stick
+	pub const fn stick_bits(&self) -> u8 {
+		self.stick_bits
+	}
+}
+
+/// Everything a model of X52 can do
+///
+/// Get it with [`X52DeviceType::capabilities`] to find out what to offer
+/// before sending anything.
+///
+/// # Examples
+///
+/// ```
+/// use rx52_core::{X52Capability, X52DeviceType};
+///
+/// let capabilities = X52DeviceType::X52.capabilities();
+/// assert!(capabilities.supports(X52Capability::Mfd));
+/// assert!(!capabilities.supports(X52Capability::ColoredLeds));
+/// assert_eq!(capabilities.report_format().report_len(), 14);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct X52Capabilities {
+	supported: u16,
+	report_format: X52ReportFormat,
+}
+
+impl X52Capabilities {
+	/// The capabilities of the given model of X52
+	pub const fn of(x52_type: X52DeviceType) -> Self {
+		let common = X52Capability::LedBrightness.bit()
+			| X52Capability::Mfd.bit()
+			| X52Capability::Clocks.bit()
+			| X52Capability::Blink.bit()
+			| X52Capability::Shift.bit();
+		match x52_type {
+			// Only the X52 Pro lets the LEDs be set
+			X52DeviceType::X52Pro => Self {
+				supported: common
+					| X52Capability::IndividualLeds.bit()
+					| X52Capability::ColoredLeds.bit()
+					| X52Capability::OnOffLeds.bit(),
+				report_format: X52ReportFormat {
+					len: X52_PRO_REPORT_LEN,
+					buttons: 39,
+					stick_bits: 10,
+				},
+			},
+			X52DeviceType::X52 => Self {
+				supported: common,
+				report_format: X52ReportFormat {
+					len: X52_REPORT_LEN,
+					buttons: 34,
+					stick_bits: 11,
+				},
+			},
+		}
+	}
+
+	/// Whether `capability` is supported
+	pub const fn supports(&self, capability: X52Capability) -> bool {
+		self.supported & capability.bit() != 0
+	}
+
+	/// Every supported capability, in the order of [`X52Capability::ALL`]
+	pub fn iter(&self) -> impl Iterator<Item = X52Capability> + '_ {
+		X52Capability::ALL.into_iter().filter(|x| self.supports(*x))
+	}
+
+	/// The layout of the input reports sent
+	pub const fn report_format(&self) -> X52ReportFormat {
+		self.report_format
+	}
+}
+
+impl X52DeviceType {
+	/// Everything this model of X52 can do
+	pub const fn capabilities(&self) -> X52Capabilities {
+		X52Capabilities::of(*self)
+	}
+}
+
+impl X52VendorCommand {
+	/// The capability the X52 needs for this command to do anything
+	pub const fn capability(&self) -> X52Capability {
+		match self {
+			Self::SetLed { .. } => X52Capability::IndividualLeds,
+			Self::SetLedBrightness(_) => X52Capability::LedBrightness,
+			Self::SetMfdBrightness(_)
+			| Self::ClearMfdLine(_)
+			| Self::WriteMfdLine { .. } => X52Capability::Mfd,
+			Self::SetShiftStatus(_) => X52Capability::Shift,
+			Self::SetBlinkStatus(_) => X52Capability::Blink,
+			Self::SetClock1 { .. }
+			| Self::SetClock2Offset { .. }
+			| Self::SetClock3Offset { .. }
+			| Self::SetDateFields { .. }
+			| Self::SetDateLastField(_) => X52Capability::Clocks,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_the_pro_has_leds() {
+		let leds = [
+			X52Capability::IndividualLeds,
+			X52Capability::ColoredLeds,
+			X52Capability::OnOffLeds,
+		];
+		let pro = X52DeviceType::X52Pro.capabilities();
+		let x52 = X52DeviceType::X52.capabilities();
+		assert_eq!(pro.iter().count(), X52Capability::ALL.len());
+		for capability in X52Capability::ALL {
+			assert_eq!(
+				x52.supports(capability),
+				!leds.contains(&capability),
+				"{capability}"
+			);
+		}
+		assert_eq!(
+			X52VendorCommand::SetLed { id: 1, on: true }.capability(),
+			X52Capability::IndividualLeds
+		);
+	}
+}
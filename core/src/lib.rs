@@ -0,0 +1,86 @@
+//! The X52 protocol, without I/O or allocation
+//!
+//! The LED, MFD, clock and date requests understood by the Logitech (formerly
+//! Saitek) X52, and parsing of the input reports it sends. This crate is
+//! `no_std` and never allocates, so the same code can drive an X52 from a
+//! microcontroller's USB host as from [rx52](https://techtricity.net/rx52),
+//! which is built on top of it.
+//!
+//! Every request is a vendor control transfer with request `0x91` and
+//! request type `0x40`, and no data. A [`X52VendorCommand`] gives the index
+//! and value to send.
+//!
+//! # Examples
+//!
+//! ```
+//! use rx52_core::{
+//!     encode_colored_led, X52ColoredLed, X52ColoredLedStatus,
+//!     X52VendorCommand,
+//! };
+//!
+//! let [red, green] =
+//!     encode_colored_led(X52ColoredLed::A, X52ColoredLedStatus::Amber);
+//! assert_eq!(red, X52VendorCommand::SetLed { id: 2, on: true });
+//! assert_eq!(green.encode(), (0xB8, 0x0301));
+//! ```
+
+#![no_std]
+#![warn(missing_docs)]
+
+#[cfg(test)]
+extern crate std;
+
+use core::fmt::{Display, Formatter};
+
+mod capability;
+mod protocol;
+mod report;
+mod types;
+mod vendor;
+
+pub use capability::{X52Capabilities, X52Capability, X52ReportFormat};
+pub use protocol::{
+	check_clock_offset, check_mfd_text, encode_colored_led, encode_date,
+	encode_mfd_text, encode_on_off_led, MAX_CLOCK_OFFSET, MFD_LINE_SIZE,
+};
+pub use report::{X52Hat, X52InputReport, X52_PRO_REPORT_LEN, X52_REPORT_LEN};
+pub use types::{
+	X52ClockFormat, X52Clocks, X52ColoredLed, X52ColoredLedStatus,
+	X52DateFormat, X52DeviceType, X52MFDLine, X52OnOffLed, X52OnOffLedStatus,
+};
+pub use vendor::{
+	X52VendorCommand, CLOCK_1_SET_COMMAND, CLOCK_2_OFFSET_COMMAND,
+	CLOCK_3_OFFSET_COMMAND, LED_SET_BRIGHTNESS_COMMAND, LED_SET_COMMAND,
+	MFD_CLEAR_LINE_COMMAND, MFD_SET_BRIGHTNESS_COMMAND,
+	SET_BLINK_STATUS_COMMAND, SET_DAY_MONTH_COMMAND, SET_SHIFT_STATUS_COMMAND,
+	SET_YEAR_COMMAND,
+};
+
+/// The request every X52 command is sent with
+pub const X52_VENDOR_REQUEST: u8 = 0x91;
+/// The request type every X52 command is sent with
+pub const X52_VENDOR_REQUEST_TYPE: u8 = 0x40;
+
+/// Errors for arguments the X52 can't show, or reports it didn't send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum X52CoreError {
+	/// The text for the MFD is longer than 16 characters
+	MFDLineTooLong,
+	/// The text for the MFD contains non-ASCII characters
+	MFDNotASCII,
+	/// The offset for clocks 2 or 3 is more than 1023 minutes either way
+	ClockOffsetTooBig,
+	/// The input report is shorter than the X52 sends
+	ReportTooShort,
+}
+
+impl Display for X52CoreError {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> core::fmt::Result {
+		fmt.write_str(match self {
+			Self::MFDLineTooLong => "The text is too long to fit on the MFD",
+			Self::MFDNotASCII => "The text contains non-ASCII characters",
+			Self::ClockOffsetTooBig => "The clock offset is too large",
+			Self::ReportTooShort => "The input report is too short",
+		})
+	}
+}
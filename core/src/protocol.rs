@@ -0,0 +1,146 @@
+//! Turning LED, MFD, clock and date settings into vendor requests
+
+use crate::{
+	X52ColoredLed, X52ColoredLedStatus, X52CoreError, X52DateFormat,
+	X52MFDLine, X52OnOffLed, X52OnOffLedStatus, X52VendorCommand,
+};
+
+/// The number of characters on a line of the MFD
+pub const MFD_LINE_SIZE: usize = 16;
+
+/// The largest offset from clock 1 the X52 can show, in minutes either way
+pub const MAX_CLOCK_OFFSET: i16 = 1023;
+
+/// Checks that `text` can be shown on a line of the MFD
+pub fn check_mfd_text(text: &[u8]) -> Result<(), X52CoreError> {
+	if !text.is_ascii() {
+		return Err(X52CoreError::MFDNotASCII);
+	}
+	if text.len() > MFD_LINE_SIZE {
+		return Err(X52CoreError::MFDLineTooLong);
+	}
+	Ok(())
+}
+
+/// Checks that `offset` is a valid offset from clock 1 for clock 2 or 3
+pub fn check_clock_offset(offset: i16) -> Result<(), X52CoreError> {
+	// The X52 only has 10 bits for the magnitude of the offset
+	if !(-MAX_CLOCK_OFFSET..=MAX_CLOCK_OFFSET).contains(&offset) {
+		Err(X52CoreError::ClockOffsetTooBig)
+	} else {
+		Ok(())
+	}
+}
+
+/// The requests setting a colored LED, red half first
+///
+/// Setting LEDs only works on an X52 Pro, which is up to the caller to check.
+pub fn encode_colored_led(
+	led: X52ColoredLed,
+	status: X52ColoredLedStatus,
+) -> [X52VendorCommand; 2] {
+	let (red, green) = led.led_ids();
+	let (red_on, green_on) = status.led_states();
+	[
+		X52VendorCommand::SetLed {
+			id: red,
+			on: red_on,
+		},
+		X52VendorCommand::SetLed {
+			id: green,
+			on: green_on,
+		},
+	]
+}
+
+/// The request setting an on/off LED
+///
+/// Setting LEDs only works on an X52 Pro, which is up to the caller to check.
+pub fn encode_on_off_led(
+	led: X52OnOffLed,
+	status: X52OnOffLedStatus,
+) -> X52VendorCommand {
+	X52VendorCommand::SetLed {
+		id: led.led_id(),
+		on: status.is_on(),
+	}
+}
+
+/// The requests showing `text` centered on a line of the MFD
+///
+/// The line is cleared first, then written two characters at a time.
+pub fn encode_mfd_text(
+	line: X52MFDLine,
+	text: &[u8],
+) -> Result<[X52VendorCommand; 1 + MFD_LINE_SIZE / 2], X52CoreError> {
+	check_mfd_text(text)?;
+	let mut padded = [b' '; MFD_LINE_SIZE];
+	// Any odd space goes on the right, like the `^` format flag
+	let left = (MFD_LINE_SIZE - text.len()) / 2;
+	padded[left..left + text.len()].copy_from_slice(text);
+	let mut commands =
+		[X52VendorCommand::ClearMfdLine(line); 1 + MFD_LINE_SIZE / 2];
+	for (command, chars) in commands[1..].iter_mut().zip(padded.chunks(2)) {
+		*command = X52VendorCommand::WriteMfdLine {
+			line,
+			chars: [chars[0], chars[1]],
+		};
+	}
+	Ok(commands)
+}
+
+/// The requests setting the date, shown in `format`
+pub fn encode_date(
+	day: u8,
+	month: u8,
+	year: u8,
+	format: X52DateFormat,
+) -> [X52VendorCommand; 2] {
+	let (first, second, last) = format.to_fields(day, month, year);
+	[
+		X52VendorCommand::SetDateFields { first, second },
+		X52VendorCommand::SetDateLastField(last),
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mfd_text_is_centered() {
+		let commands = encode_mfd_text(X52MFDLine::Line1, b"abc").unwrap();
+		let mut text = [0; MFD_LINE_SIZE];
+		for (i, command) in commands[1..].iter().enumerate() {
+			match command {
+				X52VendorCommand::WriteMfdLine { chars, .. } => {
+					text[i * 2..i * 2 + 2].copy_from_slice(chars)
+				}
+				_ => panic!("expected a write, got {command:?}"),
+			}
+		}
+		assert_eq!(
+			commands[0],
+			X52VendorCommand::ClearMfdLine(X52MFDLine::Line1)
+		);
+		assert_eq!(&text, b"      abc       ");
+		assert_eq!(
+			encode_mfd_text(X52MFDLine::Line1, &[b'a'; 17]),
+			Err(X52CoreError::MFDLineTooLong)
+		);
+		assert_eq!(
+			encode_mfd_text(X52MFDLine::Line1, "é".as_bytes()),
+			Err(X52CoreError::MFDNotASCII)
+		);
+	}
+
+	#[test]
+	fn clock_offsets_are_limited() {
+		assert_eq!(check_clock_offset(-1023), Ok(()));
+		assert_eq!(check_clock_offset(1023), Ok(()));
+		assert_eq!(
+			check_clock_offset(1024),
+			Err(X52CoreError::ClockOffsetTooBig)
+		);
+	}
+}
@@ -0,0 +1,259 @@
+//! Parsing the input reports the X52 sends on its interrupt endpoint
+//!
+//! The layout of the reports is as documented by
+//! [libx52](https://github.com/nirenjan/libx52).
+
+use crate::{X52CoreError, X52DeviceType};
+
+/// The length of an input report from a standard X52
+pub const X52_REPORT_LEN: usize = 14;
+/// The length of an input report from an X52 Pro
+pub const X52_PRO_REPORT_LEN: usize = 15;
+
+/// The position of the POV hat on the stick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum X52Hat {
+	/// Not pressed in any direction
+	Centered,
+	/// Pressed forward
+	Up,
+	/// Pressed forward and right
+	UpRight,
+	/// Pressed right
+	Right,
+	/// Pressed back and right
+	DownRight,
+	/// Pressed back
+	Down,
+	/// Pressed back and left
+	DownLeft,
+	/// Pressed left
+	Left,
+	/// Pressed forward and left
+	UpLeft,
+}
+
+impl X52Hat {
+	/// The position the X52 reports with the given value, clockwise from up
+	fn from_value(value: u8) -> Self {
+		match value {
+			1 => Self::Up,
+			2 => Self::UpRight,
+			3 => Self::Right,
+			4 => Self::DownRight,
+			5 => Self::Down,
+			6 => Self::DownLeft,
+			7 => Self::Left,
+			8 => Self::UpLeft,
+			_ => Self::Centered,
+		}
+	}
+}
+
+/// The state of every axis and button of an X52, from one input report
+///
+/// # Examples
+///
+/// ```
+/// use rx52_core::{X52DeviceType, X52Hat, X52InputReport};
+///
+/// let mut report = [0; 15];
+/// report[8] = 0b1;
+/// report[13] = 1 << 4;
+/// let report = X52InputReport::parse(X52DeviceType::X52Pro, &report).unwrap();
+/// assert!(report.button(0));
+/// assert_eq!(report.hat(), X52Hat::Up);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct X52InputReport {
+	x52_type: X52DeviceType,
+	x: u16,
+	y: u16,
+	rz: u16,
+	throttle: u8,
+	rx: u8,
+	ry: u8,
+	slider: u8,
+	buttons: u64,
+	hat: X52Hat,
+	thumb_stick: (u8, u8),
+}
+
+impl X52InputReport {
+	/// Parses an input report from an X52 of the given type
+	///
+	/// Fails with [`X52CoreError::ReportTooShort`] if `report` is shorter
+	/// than the reports that type of X52 sends. Anything after the report is
+	/// ignored.
+	pub fn parse(
+		x52_type: X52DeviceType,
+		report: &[u8],
+	) -> Result<Self, X52CoreError> {
+		if report.len() < Self::report_len(x52_type) {
+			return Err(X52CoreError::ReportTooShort);
+		}
+		let axes =
+			u32::from_le_bytes([report[0], report[1], report[2], report[3]]);
+		let mut buttons = [0; 8];
+		buttons[..5].copy_from_slice(&report[8..13]);
+		let buttons = u64::from_le_bytes(buttons);
+		let (x, y, rz, buttons, hat, thumb_stick) = match x52_type {
+			// 11 bits each for X and Y, 10 for Rz
+			X52DeviceType::X52 => (
+				axes & 0x7FF,
+				axes >> 11 & 0x7FF,
+				axes >> 22 & 0x3FF,
+				buttons & ((1 << 34) - 1),
+				report[12] >> 4,
+				report[13],
+			),
+			// 10 bits for every axis
+			X52DeviceType::X52Pro => (
+				axes & 0x3FF,
+				axes >> 10 & 0x3FF,
+				axes >> 20 & 0x3FF,
+				buttons & ((1 << 39) - 1),
+				report[13] >> 4,
+				report[14],
+			),
+		};
+		Ok(Self {
+			x52_type,
+			x: x as u16,
+			y: y as u16,
+			rz: rz as u16,
+			throttle: report[4],
+			rx: report[5],
+			ry: report[6],
+			slider: report[7],
+			buttons,
+			hat: X52Hat::from_value(hat),
+			thumb_stick: (thumb_stick & 0xF, thumb_stick >> 4),
+		})
+	}
+
+	/// The length of the input reports sent by the given type of X52
+	pub const fn report_len(x52_type: X52DeviceType) -> usize {
+		x52_type.capabilities().report_format().report_len()
+	}
+
+	/// The number of buttons on the given type of X52
+	pub const fn button_count(x52_type: X52DeviceType) -> u8 {
+		x52_type.capabilities().report_format().buttons()
+	}
+
+	/// The type of X52 the report came from
+	pub fn x52_type(&self) -> X52DeviceType {
+		self.x52_type
+	}
+
+	/// The left/right position of the stick
+	///
+	/// From 0 to 2047 on a standard X52, and 0 to 1023 on an X52 Pro.
+	pub fn x(&self) -> u16 {
+		self.x
+	}
+
+	/// The forward/back position of the stick
+	///
+	/// From 0 to 2047 on a standard X52, and 0 to 1023 on an X52 Pro.
+	pub fn y(&self) -> u16 {
+		self.y
+	}
+
+	/// The twist of the stick, from 0 to 1023
+	pub fn rz(&self) -> u16 {
+		self.rz
+	}
+
+	/// The position of the throttle
+	pub fn throttle(&self) -> u8 {
+		self.throttle
+	}
+
+	/// The rotary dial on the throttle next to the thumb
+	pub fn rx(&self) -> u8 {
+		self.rx
+	}
+
+	/// The rotary dial on the throttle above the slider
+	pub fn ry(&self) -> u8 {
+		self.ry
+	}
+
+	/// The slider on the throttle
+	pub fn slider(&self) -> u8 {
+		self.slider
+	}
+
+	/// Every button, with button `n` in bit `n`
+	pub fn buttons(&self) -> u64 {
+		self.buttons
+	}
+
+	/// Whether button `n` is pressed, counting from 0
+	pub fn button(&self, n: u8) -> bool {
+		n < 64 && self.buttons & 1 << n != 0
+	}
+
+	/// The POV hat on the stick
+	pub fn hat(&self) -> X52Hat {
+		self.hat
+	}
+
+	/// The (x, y) position of the thumb stick on the throttle, from 0 to 15
+	pub fn thumb_stick(&self) -> (u8, u8) {
+		self.thumb_stick
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn x52_reports_are_parsed() {
+		let axes: u32 = 2047 | 1024 << 11 | 512 << 22;
+		let mut report = [0; X52_REPORT_LEN];
+		report[..4].copy_from_slice(&axes.to_le_bytes());
+		report[4..8].copy_from_slice(&[1, 2, 3, 4]);
+		// Button 33 is in the low nibble, the hat in the high one
+		report[12] = 0b10 | 7 << 4;
+		report[13] = 0x3C;
+		let report =
+			X52InputReport::parse(X52DeviceType::X52, &report).unwrap();
+		assert_eq!((report.x(), report.y(), report.rz()), (2047, 1024, 512));
+		assert_eq!(
+			(report.throttle(), report.rx(), report.ry(), report.slider()),
+			(1, 2, 3, 4)
+		);
+		assert_eq!(report.buttons(), 1 << 33);
+		assert!(report.button(33));
+		assert_eq!(report.hat(), X52Hat::Left);
+		assert_eq!(report.thumb_stick(), (0xC, 0x3));
+	}
+
+	#[test]
+	fn pro_reports_are_parsed() {
+		let axes: u32 = 1023 | 1 << 10 | 1000 << 20;
+		let mut report = [0; X52_PRO_REPORT_LEN];
+		report[..4].copy_from_slice(&axes.to_le_bytes());
+		report[12] = 0x40;
+		report[13] = 5 << 4;
+		report[14] = 0xF0;
+		let report =
+			X52InputReport::parse(X52DeviceType::X52Pro, &report).unwrap();
+		assert_eq!((report.x(), report.y(), report.rz()), (1023, 1, 1000));
+		assert_eq!(report.buttons(), 1 << 38);
+		assert_eq!(report.hat(), X52Hat::Down);
+		assert_eq!(report.thumb_stick(), (0, 0xF));
+	}
+
+	#[test]
+	fn short_reports_are_rejected() {
+		assert_eq!(
+			X52InputReport::parse(X52DeviceType::X52Pro, &[0; 14]),
+			Err(X52CoreError::ReportTooShort)
+		);
+	}
+}
@@ -0,0 +1,256 @@
+//! The parts of an X52 commands are sent to
+
+use core::fmt::{Display, Formatter};
+
+/// The physical type of an X52 device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum X52DeviceType {
+	/// The X52 Pro, with more features
+	X52Pro,
+	/// The standard X52
+	X52,
+}
+
+/// The color options for each LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52ColoredLedStatus {
+	/// Turns the LED off
+	Off,
+	/// Sets the LED green
+	Green,
+	/// Sets the LED red
+	Red,
+	/// Sets the LED amber
+	Amber,
+}
+
+/// The options for an LED which can only be on or off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52OnOffLedStatus {
+	/// Turns the LED off
+	Off,
+	/// Turns the LED on
+	On,
+}
+
+/// The colored LEDs on the X52
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52ColoredLed {
+	/// The A button on the stick
+	A,
+	/// The B button on the stick
+	B,
+	/// The D button on the throttle
+	D,
+	/// The E button on the throttle
+	E,
+	/// The LED between the T1 and T2 switches
+	T1,
+	/// The LED between the T3 and T4 switches
+	T3,
+	/// The LED between the T5 and T6 switches
+	T5,
+	/// The LED in the middle of the POV hat
+	PovHat,
+	/// The clutch button on the throttle (i button)
+	Clutch,
+}
+
+/// The on/off LEDs on the X52
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52OnOffLed {
+	/// The fire button on the stick
+	Fire,
+	/// The LED inside the throttle
+	Throttle,
+}
+
+/// The line of the MFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52MFDLine {
+	/// The first line
+	Line1,
+	/// The second line
+	Line2,
+	/// The third line
+	Line3,
+}
+
+/// The date format for the MFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52DateFormat {
+	/// Day, month, year
+	DDMMYY,
+	/// Month, day, year
+	MMDDYY,
+	/// Year, month, day
+	YYMMDD,
+}
+
+/// The clock format for the MFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52ClockFormat {
+	/// Twelve hour clock
+	Hr12,
+	/// 24 hour clock
+	Hr24,
+}
+
+/// The clocks on the X52
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum X52Clocks {
+	/// Clock 1 is the real clock
+	Clock1,
+	/// Clock 2 is an offset from clock 1
+	Clock2,
+	/// Clock 3 is an offset from clock 1
+	Clock3,
+}
+
+impl X52ColoredLed {
+	/// Every colored LED, in the order they are numbered by the X52
+	pub const ALL: [X52ColoredLed; 9] = [
+		Self::A,
+		Self::B,
+		Self::D,
+		Self::E,
+		Self::T1,
+		Self::T3,
+		Self::T5,
+		Self::PovHat,
+		Self::Clutch,
+	];
+
+	/// The IDs the X52 knows the red and green halves of the LED by
+	pub const fn led_ids(&self) -> (u8, u8) {
+		match self {
+			Self::A => (2, 3),
+			Self::B => (4, 5),
+			Self::D => (6, 7),
+			Self::E => (8, 9),
+			Self::T1 => (10, 11),
+			Self::T3 => (12, 13),
+			Self::T5 => (14, 15),
+			Self::PovHat => (16, 17),
+			Self::Clutch => (18, 19),
+		}
+	}
+
+	/// The LED with the given red and green IDs, if there is one
+	pub fn from_led_ids(red: u8, green: u8) -> Option<Self> {
+		Self::ALL.into_iter().find(|x| x.led_ids() == (red, green))
+	}
+}
+
+impl X52ColoredLedStatus {
+	/// Whether the red and green halves of the LED are on
+	pub const fn led_states(&self) -> (bool, bool) {
+		match self {
+			Self::Off => (false, false),
+			Self::Red => (true, false),
+			Self::Green => (false, true),
+			Self::Amber => (true, true),
+		}
+	}
+
+	/// The color shown with the red and green halves of the LED on or off
+	pub const fn from_led_states(red: bool, green: bool) -> Self {
+		match (red, green) {
+			(false, false) => Self::Off,
+			(true, false) => Self::Red,
+			(false, true) => Self::Green,
+			(true, true) => Self::Amber,
+		}
+	}
+}
+
+impl X52OnOffLed {
+	/// Every on/off LED
+	pub const ALL: [X52OnOffLed; 2] = [Self::Fire, Self::Throttle];
+
+	/// The ID the X52 knows the LED by
+	pub const fn led_id(&self) -> u8 {
+		match self {
+			Self::Fire => 1,
+			Self::Throttle => 20,
+		}
+	}
+
+	/// The LED with the given ID, if it is an on/off LED
+	pub fn from_led_id(id: u8) -> Option<Self> {
+		Self::ALL.into_iter().find(|x| x.led_id() == id)
+	}
+}
+
+impl X52OnOffLedStatus {
+	/// Whether the LED is on
+	pub const fn is_on(&self) -> bool {
+		matches!(self, Self::On)
+	}
+
+	/// The status of an LED which is on or not
+	pub const fn from_on(on: bool) -> Self {
+		if on {
+			Self::On
+		} else {
+			Self::Off
+		}
+	}
+}
+
+impl X52MFDLine {
+	/// Every line of the MFD, from top to bottom
+	pub const ALL: [X52MFDLine; 3] = [Self::Line1, Self::Line2, Self::Line3];
+}
+
+impl X52Clocks {
+	/// Every clock
+	pub const ALL: [X52Clocks; 3] = [Self::Clock1, Self::Clock2, Self::Clock3];
+}
+
+impl X52DateFormat {
+	/// The (first, second, last) fields the date is shown in, from left to
+	/// right
+	pub const fn to_fields(
+		&self,
+		day: u8,
+		month: u8,
+		year: u8,
+	) -> (u8, u8, u8) {
+		match self {
+			Self::DDMMYY => (day, month, year),
+			Self::MMDDYY => (month, day, year),
+			Self::YYMMDD => (year, month, day),
+		}
+	}
+
+	/// The (day, month, year) of a date shown as the given fields
+	pub const fn from_fields(
+		&self,
+		(first, second, last): (u8, u8, u8),
+	) -> (u8, u8, u8) {
+		match self {
+			Self::DDMMYY => (first, second, last),
+			Self::MMDDYY => (second, first, last),
+			Self::YYMMDD => (last, second, first),
+		}
+	}
+}
+
+impl Display for X52DeviceType {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), core::fmt::Error> {
+		write!(
+			fmt,
+			"{}",
+			if self == &Self::X52 { "X52" } else { "X52 Pro" }
+		)
+	}
+}
@@ -0,0 +1,490 @@
+//! The vendor requests understood by the X52
+
+use crate::{X52ColoredLed, X52MFDLine, X52OnOffLed};
+use core::fmt::{Display, Formatter};
+
+/// The command used to set an LED
+pub const LED_SET_COMMAND: u16 = 0xB8;
+/// The command to set the brightness of the LEDs
+pub const LED_SET_BRIGHTNESS_COMMAND: u16 = 0xB2;
+/// The command to set the brightness of the MFD
+pub const MFD_SET_BRIGHTNESS_COMMAND: u16 = 0xB1;
+/// The command used to clear a line of the MFD
+pub const MFD_CLEAR_LINE_COMMAND: u16 = 0x08;
+/// The command to set the shift indicator on the MFD
+pub const SET_SHIFT_STATUS_COMMAND: u16 = 0xFD;
+/// The command to set the blinking of the throttle and POV hat LEDs
+pub const SET_BLINK_STATUS_COMMAND: u16 = 0xB4;
+/// The command to set clock 1
+pub const CLOCK_1_SET_COMMAND: u16 = 0xC0;
+/// The command to set clock 2's offset from clock 1
+pub const CLOCK_2_OFFSET_COMMAND: u16 = 0xC1;
+/// The command to set clock 3's offset from clock 1
+pub const CLOCK_3_OFFSET_COMMAND: u16 = 0xC2;
+/// Sets the day and month on the MFD
+pub const SET_DAY_MONTH_COMMAND: u16 = 0xC4;
+/// Sets the year on the MFD
+pub const SET_YEAR_COMMAND: u16 = 0xC8;
+
+/// The value of a request turning something on
+const VALUE_ON: u16 = 0x51;
+/// The value of a request turning something off
+const VALUE_OFF: u16 = 0x50;
+/// The bit of a clock request selecting the 24 hour format
+const CLOCK_24H_BIT: u16 = 1 << 15;
+/// The bit of a clock offset request marking the offset as negative
+const CLOCK_OFFSET_NEGATIVE_BIT: u16 = 1 << 10;
+/// The bits of a clock offset request holding the size of the offset
+const CLOCK_OFFSET_MASK: u16 = CLOCK_OFFSET_NEGATIVE_BIT - 1;
+
+/// The index the X52 writes a line of the MFD with
+fn mfd_line_index(line: &X52MFDLine) -> u16 {
+	match line {
+		X52MFDLine::Line1 => 0xD1,
+		X52MFDLine::Line2 => 0xD2,
+		X52MFDLine::Line3 => 0xD4,
+	}
+}
+
+/// The line of the MFD written with the given index
+fn mfd_line_from_index(index: u16) -> Option<X52MFDLine> {
+	X52MFDLine::ALL
+		.into_iter()
+		.find(|x| mfd_line_index(x) == index)
+}
+
+/// Encodes an on/off value
+fn encode_bool(enabled: bool) -> u16 {
+	if enabled {
+		VALUE_ON
+	} else {
+		VALUE_OFF
+	}
+}
+
+/// Decodes an on/off value
+fn decode_bool(value: u16) -> Option<bool> {
+	match value {
+		VALUE_ON => Some(true),
+		VALUE_OFF => Some(false),
+		_ => None,
+	}
+}
+
+/// Encodes the offset of clock 2 or 3 from clock 1
+fn encode_clock_offset(offset: i16, use_24h: bool) -> u16 {
+	let mut value = offset.unsigned_abs() & CLOCK_OFFSET_MASK;
+	if offset < 0 {
+		value |= CLOCK_OFFSET_NEGATIVE_BIT;
+	}
+	if use_24h {
+		value |= CLOCK_24H_BIT;
+	}
+	value
+}
+
+/// Decodes the offset of clock 2 or 3 from clock 1, with its format
+fn decode_clock_offset(value: u16) -> (i16, bool) {
+	let minutes = (value & CLOCK_OFFSET_MASK) as i16;
+	let offset = match value & CLOCK_OFFSET_NEGATIVE_BIT {
+		0 => minutes,
+		_ => -minutes,
+	};
+	(offset, value & CLOCK_24H_BIT != 0)
+}
+
+/// Describes the LED with the given ID, like `Clutch green`
+fn write_led(fmt: &mut Formatter<'_>, id: u8) -> core::fmt::Result {
+	if let Some(led) = X52OnOffLed::from_led_id(id) {
+		return write!(fmt, "{led:?}");
+	}
+	for led in X52ColoredLed::ALL {
+		match led.led_ids() {
+			(red, _) if red == id => return write!(fmt, "{led:?} red"),
+			(_, green) if green == id => return write!(fmt, "{led:?} green"),
+			_ => {}
+		}
+	}
+	write!(fmt, "{id}")
+}
+
+/// Describes a clock format, as shown after clock settings
+fn describe_format(use_24h: bool) -> &'static str {
+	if use_24h {
+		"24h"
+	} else {
+		"12h"
+	}
+}
+
+/// Describes an on/off value
+fn describe_bool(enabled: bool) -> &'static str {
+	if enabled {
+		"on"
+	} else {
+		"off"
+	}
+}
+
+/// A single vendor request the X52 is known to understand
+///
+/// This is the catalogue of every request rx52 sends, at the level of the USB
+/// protocol rather than the friendlier methods of its driver. For
+/// example, a colored LED is really two LEDs, a red and a green one, which
+/// are each turned on and off with [`X52VendorCommand::SetLed`].
+///
+/// Use [`X52VendorCommand::encode`] to get the index and value sent to the
+/// X52, and [`X52VendorCommand::decode`] to go the other way. Displaying a
+/// command describes it, like `LED Clutch green off`.
+///
+/// # Examples
+///
+/// ```
+/// use rx52_core::{X52MFDLine, X52VendorCommand};
+///
+/// let command = X52VendorCommand::ClearMfdLine(X52MFDLine::Line2);
+/// assert_eq!(command.encode(), (0xDA, 0));
+/// assert_eq!(X52VendorCommand::decode(0xDA, 0), Some(command));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum X52VendorCommand {
+	/// Turns a single LED on or off, only on the X52 Pro
+	///
+	/// Each colored LED is made up of a red LED, with an even ID from 2 to
+	/// 18, and a green LED with the ID after it. The fire button LED has the
+	/// ID 1 and the throttle LED has the ID 20.
+	SetLed {
+		/// The ID of the LED
+		id: u8,
+		/// Whether to turn the LED on
+		on: bool,
+	},
+	/// Sets the brightness of the LEDs
+	SetLedBrightness(u8),
+	/// Sets the brightness of the MFD
+	SetMfdBrightness(u8),
+	/// Clears a line of the MFD
+	ClearMfdLine(X52MFDLine),
+	/// Appends two characters to a line of the MFD
+	WriteMfdLine {
+		/// The line to write to
+		line: X52MFDLine,
+		/// The characters to write
+		chars: [u8; 2],
+	},
+	/// Turns the shift indicator on the MFD on or off
+	SetShiftStatus(bool),
+	/// Turns blinking of the throttle and POV hat LEDs on or off
+	SetBlinkStatus(bool),
+	/// Sets clock 1
+	SetClock1 {
+		/// The hour, from 0 to 23
+		hour: u8,
+		/// The minute, from 0 to 59
+		minute: u8,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// Sets the offset of clock 2 from clock 1
+	SetClock2Offset {
+		/// The offset in minutes, up to 1023 either way
+		offset: i16,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// Sets the offset of clock 3 from clock 1
+	SetClock3Offset {
+		/// The offset in minutes, up to 1023 either way
+		offset: i16,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// Sets the first two fields of the date
+	///
+	/// Which field is the day, month or year is up to the caller, see
+	/// [`X52DateFormat`](crate::X52DateFormat).
+	SetDateFields {
+		/// The leftmost field
+		first: u8,
+		/// The middle field
+		second: u8,
+	},
+	/// Sets the last field of the date
+	SetDateLastField(u8),
+}
+
+impl X52VendorCommand {
+	/// The index and value of the request
+	pub fn encode(&self) -> (u16, u16) {
+		match *self {
+			Self::SetLed { id, on } => {
+				(LED_SET_COMMAND, (id as u16) << 8 | on as u16)
+			}
+			Self::SetLedBrightness(x) => (LED_SET_BRIGHTNESS_COMMAND, x as u16),
+			Self::SetMfdBrightness(x) => (MFD_SET_BRIGHTNESS_COMMAND, x as u16),
+			Self::ClearMfdLine(line) => {
+				(mfd_line_index(&line) | MFD_CLEAR_LINE_COMMAND, 0)
+			}
+			Self::WriteMfdLine { line, chars } => (
+				mfd_line_index(&line),
+				(chars[1] as u16) << 8 | chars[0] as u16,
+			),
+			Self::SetShiftStatus(x) => {
+				(SET_SHIFT_STATUS_COMMAND, encode_bool(x))
+			}
+			Self::SetBlinkStatus(x) => {
+				(SET_BLINK_STATUS_COMMAND, encode_bool(x))
+			}
+			Self::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			} => (
+				CLOCK_1_SET_COMMAND,
+				if use_24h { CLOCK_24H_BIT } else { 0 }
+					| ((hour as u16) & 0x7F) << 8
+					| minute as u16,
+			),
+			Self::SetClock2Offset { offset, use_24h } => {
+				(CLOCK_2_OFFSET_COMMAND, encode_clock_offset(offset, use_24h))
+			}
+			Self::SetClock3Offset { offset, use_24h } => {
+				(CLOCK_3_OFFSET_COMMAND, encode_clock_offset(offset, use_24h))
+			}
+			Self::SetDateFields { first, second } => {
+				(SET_DAY_MONTH_COMMAND, (second as u16) << 8 | first as u16)
+			}
+			Self::SetDateLastField(x) => (SET_YEAR_COMMAND, x as u16),
+		}
+	}
+
+	/// Turns the index and value of a request back into a command
+	///
+	/// Returns [`None`] for requests which are not in the catalogue.
+	pub fn decode(index: u16, value: u16) -> Option<Self> {
+		let [low, high] = value.to_le_bytes();
+		// Requests with a single byte argument leave the high byte empty
+		let byte = || (high == 0).then_some(low);
+		Some(match index {
+			LED_SET_COMMAND => Self::SetLed {
+				id: high,
+				on: match low {
+					0 => false,
+					1 => true,
+					_ => return None,
+				},
+			},
+			LED_SET_BRIGHTNESS_COMMAND => Self::SetLedBrightness(byte()?),
+			MFD_SET_BRIGHTNESS_COMMAND => Self::SetMfdBrightness(byte()?),
+			SET_SHIFT_STATUS_COMMAND => {
+				Self::SetShiftStatus(decode_bool(value)?)
+			}
+			SET_BLINK_STATUS_COMMAND => {
+				Self::SetBlinkStatus(decode_bool(value)?)
+			}
+			CLOCK_1_SET_COMMAND => Self::SetClock1 {
+				hour: high & 0x7F,
+				minute: low,
+				use_24h: value & CLOCK_24H_BIT != 0,
+			},
+			CLOCK_2_OFFSET_COMMAND => {
+				let (offset, use_24h) = decode_clock_offset(value);
+				Self::SetClock2Offset { offset, use_24h }
+			}
+			CLOCK_3_OFFSET_COMMAND => {
+				let (offset, use_24h) = decode_clock_offset(value);
+				Self::SetClock3Offset { offset, use_24h }
+			}
+			SET_DAY_MONTH_COMMAND => Self::SetDateFields {
+				first: low,
+				second: high,
+			},
+			SET_YEAR_COMMAND => Self::SetDateLastField(byte()?),
+			_ => match mfd_line_from_index(index) {
+				Some(line) => Self::WriteMfdLine {
+					line,
+					chars: [low, high],
+				},
+				None => Self::ClearMfdLine(mfd_line_from_index(
+					index & !MFD_CLEAR_LINE_COMMAND,
+				)?),
+			},
+		})
+	}
+
+	/// The name of the command with the given index, like `LED_SET_COMMAND`
+	///
+	/// Returns [`None`] for indices which are not in the catalogue.
+	pub fn command_name(index: u16) -> Option<&'static str> {
+		Some(match index {
+			LED_SET_COMMAND => "LED_SET_COMMAND",
+			LED_SET_BRIGHTNESS_COMMAND => "LED_SET_BRIGHTNESS_COMMAND",
+			MFD_SET_BRIGHTNESS_COMMAND => "MFD_SET_BRIGHTNESS_COMMAND",
+			SET_SHIFT_STATUS_COMMAND => "SET_SHIFT_STATUS_COMMAND",
+			SET_BLINK_STATUS_COMMAND => "SET_BLINK_STATUS_COMMAND",
+			CLOCK_1_SET_COMMAND => "CLOCK_1_SET_COMMAND",
+			CLOCK_2_OFFSET_COMMAND => "CLOCK_2_OFFSET_COMMAND",
+			CLOCK_3_OFFSET_COMMAND => "CLOCK_3_OFFSET_COMMAND",
+			SET_DAY_MONTH_COMMAND => "SET_DAY_MONTH_COMMAND",
+			SET_YEAR_COMMAND => "SET_YEAR_COMMAND",
+			_ if mfd_line_from_index(index).is_some() => "MFD_WRITE_LINE",
+			_ if mfd_line_from_index(index & !MFD_CLEAR_LINE_COMMAND)
+				.is_some() =>
+			{
+				"MFD_CLEAR_LINE_COMMAND"
+			}
+			_ => return None,
+		})
+	}
+
+	/// The name of the command, like `LED_SET_COMMAND`
+	pub fn name(&self) -> &'static str {
+		Self::command_name(self.encode().0)
+			.expect("every command in the catalogue has a name")
+	}
+}
+
+impl Display for X52VendorCommand {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> core::fmt::Result {
+		match *self {
+			Self::SetLed { id, on } => {
+				write!(fmt, "LED ")?;
+				write_led(fmt, id)?;
+				write!(fmt, " {}", describe_bool(on))
+			}
+			Self::SetLedBrightness(x) => write!(fmt, "LED brightness {x}"),
+			Self::SetMfdBrightness(x) => write!(fmt, "MFD brightness {x}"),
+			Self::ClearMfdLine(line) => {
+				write!(fmt, "MFD line {} clear", line as u8 + 1)
+			}
+			Self::WriteMfdLine { line, chars } => write!(
+				fmt,
+				"MFD line {} chars '{}'",
+				line as u8 + 1,
+				chars.escape_ascii()
+			),
+			Self::SetShiftStatus(x) => {
+				write!(fmt, "shift {}", describe_bool(x))
+			}
+			Self::SetBlinkStatus(x) => {
+				write!(fmt, "blink {}", describe_bool(x))
+			}
+			Self::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			} => write!(
+				fmt,
+				"clock 1 {hour:02}:{minute:02}, {}",
+				describe_format(use_24h)
+			),
+			Self::SetClock2Offset { offset, use_24h } => write!(
+				fmt,
+				"clock 2 offset {offset} min, {}",
+				describe_format(use_24h)
+			),
+			Self::SetClock3Offset { offset, use_24h } => write!(
+				fmt,
+				"clock 3 offset {offset} min, {}",
+				describe_format(use_24h)
+			),
+			Self::SetDateFields { first, second } => {
+				write!(fmt, "date fields {first} {second}")
+			}
+			Self::SetDateLastField(x) => write!(fmt, "date last field {x}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::string::ToString;
+
+	#[test]
+	fn catalogue_round_trips() {
+		let commands = [
+			X52VendorCommand::SetLed { id: 18, on: true },
+			X52VendorCommand::SetLedBrightness(64),
+			X52VendorCommand::SetMfdBrightness(128),
+			X52VendorCommand::ClearMfdLine(X52MFDLine::Line3),
+			X52VendorCommand::WriteMfdLine {
+				line: X52MFDLine::Line1,
+				chars: *b"Hi",
+			},
+			X52VendorCommand::SetShiftStatus(true),
+			X52VendorCommand::SetBlinkStatus(false),
+			X52VendorCommand::SetClock1 {
+				hour: 23,
+				minute: 59,
+				use_24h: true,
+			},
+			X52VendorCommand::SetClock2Offset {
+				offset: -300,
+				use_24h: false,
+			},
+			X52VendorCommand::SetClock3Offset {
+				offset: 90,
+				use_24h: true,
+			},
+			X52VendorCommand::SetDateFields {
+				first: 31,
+				second: 12,
+			},
+			X52VendorCommand::SetDateLastField(24),
+		];
+		for command in commands {
+			let (index, value) = command.encode();
+			assert_eq!(X52VendorCommand::decode(index, value), Some(command));
+		}
+	}
+
+	#[test]
+	fn clock_offsets_are_sign_and_magnitude() {
+		let encode = |offset| {
+			X52VendorCommand::SetClock2Offset {
+				offset,
+				use_24h: true,
+			}
+			.encode()
+		};
+		assert_eq!(encode(60), (0xC1, 0x8000 | 60));
+		assert_eq!(encode(-60), (0xC1, 0x8000 | 0x400 | 60));
+	}
+
+	#[test]
+	fn command_names() {
+		assert_eq!(
+			X52VendorCommand::command_name(0xB8),
+			Some("LED_SET_COMMAND")
+		);
+		assert_eq!(
+			X52VendorCommand::command_name(0xD4),
+			Some("MFD_WRITE_LINE")
+		);
+		assert_eq!(
+			X52VendorCommand::command_name(0xD9),
+			Some("MFD_CLEAR_LINE_COMMAND")
+		);
+		assert_eq!(X52VendorCommand::command_name(0x00), None);
+		assert_eq!(X52VendorCommand::decode(0x00, 0), None);
+	}
+
+	#[test]
+	fn commands_describe_themselves() {
+		let describe = |index, value| {
+			X52VendorCommand::decode(index, value).unwrap().to_string()
+		};
+		assert_eq!(describe(0xB8, 0x1300), "LED Clutch green off");
+		assert_eq!(describe(0xB8, 0x0101), "LED Fire on");
+		assert_eq!(
+			describe(0xD2, u16::from_le_bytes(*b"AB")),
+			"MFD line 2 chars 'AB'"
+		);
+		assert_eq!(
+			describe(0xC1, 0x8000 | 0x400 | 60),
+			"clock 2 offset -60 min, 24h"
+		);
+		assert_eq!(describe(0xC0, 0x0905), "clock 1 09:05, 12h");
+	}
+}
@@ -0,0 +1,290 @@
+//! An async front-end for [`X52Driver`], built on tokio
+
+use crate::{
+	Error, UsbTransport, X52Capabilities, X52ColoredLed, X52ColoredLedStatus,
+	X52Command, X52DateFormat, X52DeviceType, X52Driver, X52HotplugEvent,
+	X52HotplugWatcher, X52MFDLine, X52OnOffLed, X52OnOffLedStatus,
+	X52Transport,
+};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task;
+
+/// How often the event forwarding thread checks if the stream was dropped
+const EVENT_STREAM_POLL: Duration = Duration::from_millis(200);
+
+/// An [`X52Driver`] whose methods return futures instead of blocking
+///
+/// Requires the `tokio` feature.
+///
+/// rusb only offers synchronous transfers, so each command runs on tokio's
+/// blocking thread pool, rather than as a libusb asynchronous transfer.
+/// Commands sent from different tasks are still sent one at a time by the
+/// transport, in whatever order they reach it.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example() -> Result<(), rx52::Error> {
+/// use rx52::{X52AsyncDriver, X52MFDLine};
+///
+/// let driver = X52AsyncDriver::new(rx52::get_devices()?[0].open()?);
+/// driver.set_mfd_text(&X52MFDLine::Line1, "Hello".to_string()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct X52AsyncDriver<T: X52Transport = UsbTransport> {
+	driver: Arc<X52Driver<T>>,
+}
+
+impl<T: X52Transport> Clone for X52AsyncDriver<T> {
+	fn clone(&self) -> Self {
+		Self {
+			driver: self.driver.clone(),
+		}
+	}
+}
+
+impl<T: X52Transport + Send + Sync + 'static> X52AsyncDriver<T> {
+	/// Wraps a blocking driver
+	pub fn new(driver: X52Driver<T>) -> Self {
+		Self {
+			driver: Arc::new(driver),
+		}
+	}
+
+	/// The blocking driver underneath
+	pub fn driver(&self) -> &X52Driver<T> {
+		&self.driver
+	}
+
+	/// Runs `f` with the driver on the blocking thread pool
+	async fn run<R, F>(&self, f: F) -> Result<R, Error>
+	where
+		R: Send + 'static,
+		F: FnOnce(&X52Driver<T>) -> Result<R, Error> + Send + 'static,
+	{
+		let driver = self.driver.clone();
+		match task::spawn_blocking(move || f(&driver)).await {
+			Ok(result) => result,
+			Err(err) if err.is_panic() => {
+				std::panic::resume_unwind(err.into_panic())
+			}
+			Err(_) => {
+				Err("The runtime shut down before the command was sent".into())
+			}
+		}
+	}
+
+	/// See [`X52Driver::toggle_led_on_off`]
+	pub async fn toggle_led_on_off(
+		&self,
+		led: &X52OnOffLed,
+		status: &X52OnOffLedStatus,
+	) -> Result<(), Error> {
+		let (led, status) = (*led, *status);
+		self.run(move |x| x.toggle_led_on_off(&led, &status)).await
+	}
+
+	/// See [`X52Driver::toggle_led_colored`]
+	pub async fn toggle_led_colored(
+		&self,
+		led: &X52ColoredLed,
+		status: &X52ColoredLedStatus,
+	) -> Result<(), Error> {
+		let (led, status) = (*led, *status);
+		self.run(move |x| x.toggle_led_colored(&led, &status)).await
+	}
+
+	/// See [`X52Driver::clear_mfd_line`]
+	pub async fn clear_mfd_line(&self, line: &X52MFDLine) -> Result<(), Error> {
+		let line = *line;
+		self.run(move |x| x.clear_mfd_line(&line)).await
+	}
+
+	/// See [`X52Driver::set_mfd_text`]
+	pub async fn set_mfd_text(
+		&self,
+		line: &X52MFDLine,
+		text: String,
+	) -> Result<(), Error> {
+		let line = *line;
+		self.run(move |x| x.set_mfd_text(&line, text)).await
+	}
+
+	/// See [`X52Driver::set_led_brightness`]
+	pub async fn set_led_brightness(
+		&self,
+		brightness: u8,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_led_brightness(brightness)).await
+	}
+
+	/// See [`X52Driver::set_mfd_brightness`]
+	pub async fn set_mfd_brightness(
+		&self,
+		brightness: u8,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_mfd_brightness(brightness)).await
+	}
+
+	/// See [`X52Driver::set_shift_status`]
+	pub async fn set_shift_status(&self, enabled: bool) -> Result<(), Error> {
+		self.run(move |x| x.set_shift_status(enabled)).await
+	}
+
+	/// See [`X52Driver::set_blink_status`]
+	pub async fn set_blink_status(&self, enabled: bool) -> Result<(), Error> {
+		self.run(move |x| x.set_blink_status(enabled)).await
+	}
+
+	/// See [`X52Driver::set_clock_1`]
+	pub async fn set_clock_1(
+		&self,
+		hour: u8,
+		minute: u8,
+		use_24h: bool,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_clock_1(hour, minute, use_24h))
+			.await
+	}
+
+	/// See [`X52Driver::set_clock_2_offset`]
+	pub async fn set_clock_2_offset(
+		&self,
+		offset: i16,
+		use_24h: bool,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_clock_2_offset(offset, use_24h))
+			.await
+	}
+
+	/// See [`X52Driver::set_clock_3_offset`]
+	pub async fn set_clock_3_offset(
+		&self,
+		offset: i16,
+		use_24h: bool,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_clock_3_offset(offset, use_24h))
+			.await
+	}
+
+	/// See [`X52Driver::set_date`]
+	pub async fn set_date(
+		&self,
+		day: u8,
+		month: u8,
+		year: u8,
+		format: X52DateFormat,
+	) -> Result<(), Error> {
+		self.run(move |x| x.set_date(day, month, year, format))
+			.await
+	}
+
+	/// See [`X52Driver::execute`]
+	pub async fn execute(&self, command: X52Command) -> Result<(), Error> {
+		self.run(move |x| x.execute(&command)).await
+	}
+
+	/// See [`X52Driver::x52_type`]
+	pub fn x52_type(&self) -> &'static X52DeviceType {
+		self.driver.x52_type()
+	}
+
+	/// See [`X52Driver::capabilities`]
+	pub fn capabilities(&self) -> X52Capabilities {
+		self.driver.capabilities()
+	}
+}
+
+/// A [`Stream`] of X52s being plugged in and unplugged
+///
+/// Requires the `tokio` feature. This wraps an [`X52HotplugWatcher`], see
+/// there for details.
+pub struct X52EventStream {
+	receiver: UnboundedReceiver<X52HotplugEvent>,
+}
+
+impl X52EventStream {
+	/// Starts watching for X52s
+	pub fn new() -> Result<Self, Error> {
+		Ok(Self::from_watcher(X52HotplugWatcher::new()?))
+	}
+
+	/// Turns an existing watcher into a stream
+	///
+	/// The stream ends if the watcher thread stops.
+	pub fn from_watcher(watcher: X52HotplugWatcher) -> Self {
+		let (sender, receiver) = mpsc::unbounded_channel();
+		thread::spawn(move || {
+			while !sender.is_closed() {
+				match watcher.try_recv_timeout(EVENT_STREAM_POLL) {
+					Ok(event) => {
+						if sender.send(event).is_err() {
+							break;
+						}
+					}
+					Err(RecvTimeoutError::Timeout) => {}
+					Err(RecvTimeoutError::Disconnected) => break,
+				}
+			}
+		});
+		Self { receiver }
+	}
+}
+
+impl Stream for X52EventStream {
+	type Item = X52HotplugEvent;
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MockTransport;
+	use std::future;
+
+	#[test]
+	fn async_commands_reach_transport() {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap();
+		let driver = X52AsyncDriver::new(X52Driver::new(
+			MockTransport::new(),
+			&X52DeviceType::X52,
+		));
+		runtime.block_on(async {
+			driver.set_shift_status(true).await.unwrap();
+			driver.set_mfd_brightness(3).await.unwrap();
+		});
+		assert_eq!(
+			driver.driver().transport().requests(),
+			vec![(0xFD, 0x51), (0xB1, 3)]
+		);
+	}
+
+	#[test]
+	fn event_stream_ends_with_watcher() {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap();
+		let mut stream =
+			X52EventStream::from_watcher(X52HotplugWatcher::stopped());
+		let next = runtime.block_on(future::poll_fn(|cx| {
+			Pin::new(&mut stream).poll_next(cx)
+		}));
+		assert!(next.is_none());
+	}
+}
@@ -0,0 +1,326 @@
+//! Recording the USB traffic of a driver to a pcapng file
+
+use crate::transport::X52_REPORT_ENDPOINT;
+use crate::Error;
+use rx52_core::X52_VENDOR_REQUEST;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The block types used, from the pcapng specification
+pub(crate) const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+pub(crate) const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+pub(crate) const ENHANCED_PACKET_BLOCK: u32 = 6;
+/// Written as is, so readers can tell the byte order of the file
+pub(crate) const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+/// LINKTYPE_USB_LINUX_MMAPPED, the usbmon format Wireshark reads
+pub(crate) const LINKTYPE_USB_LINUX_MMAPPED: u16 = 220;
+/// The size of the usbmon header in front of every packet
+pub(crate) const USBMON_HEADER_LEN: usize = 64;
+
+/// The usbmon transfer types
+const XFER_INTERRUPT: u8 = 1;
+const XFER_CONTROL: u8 = 2;
+
+/// The Linux errno values usbmon reports failed transfers with
+const EIO: i32 = 5;
+const ENODEV: i32 = 19;
+const EPIPE: i32 = 32;
+const ETIMEDOUT: i32 = 110;
+/// What usbmon reports as the status of a transfer which isn't done yet
+const EINPROGRESS: i32 = 115;
+
+/// A single usbmon event
+struct UsbEvent<'a> {
+	kind: u8,
+	xfer_type: u8,
+	endpoint: u8,
+	setup: Option<[u8; 8]>,
+	status: i32,
+	length: u32,
+	data: &'a [u8],
+}
+
+/// A pcapng file which USB traffic to and from an X52 is written to
+///
+/// Every vendor request the driver sends is recorded as a control transfer,
+/// and every input report it reads as an interrupt transfer, in the usbmon
+/// format of Linux. Wireshark and tshark open the file like a capture made
+/// with usbmon, on any platform.
+///
+/// Failing to write the file never fails the driver. Recording stops at the
+/// first error instead, which [`X52Capture::finish`] returns.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::X52Capture;
+///
+/// let mut driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// driver.start_capture(X52Capture::create("x52.pcapng").unwrap());
+/// driver.set_led_brightness(64).unwrap();
+/// driver.stop_capture().unwrap().finish().unwrap();
+/// ```
+pub struct X52Capture {
+	writer: Box<dyn Write + Send>,
+	/// The ID given to the next transfer, to pair submissions and completions
+	next_id: u64,
+	/// The first error writing to `writer`
+	error: Option<std::io::Error>,
+}
+
+impl X52Capture {
+	/// Creates a capture writing to the file at `path`, replacing it if it
+	/// exists
+	pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		Self::new(BufWriter::new(File::create(path)?))
+	}
+
+	/// Creates a capture writing to `writer`
+	pub fn new<W: Write + Send + 'static>(writer: W) -> Result<Self, Error> {
+		let mut capture = Self {
+			writer: Box::new(writer),
+			next_id: 0,
+			error: None,
+		};
+		capture.write_headers()?;
+		Ok(capture)
+	}
+
+	/// Flushes everything written so far, and returns the first error there
+	/// was writing it
+	pub fn finish(mut self) -> Result<(), Error> {
+		if let Some(err) = self.error.take() {
+			return Err(err.into());
+		}
+		Ok(self.writer.flush()?)
+	}
+
+	/// Writes the section header and the single interface of the file
+	fn write_headers(&mut self) -> std::io::Result<()> {
+		let mut shb = Vec::with_capacity(16);
+		shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+		shb.extend_from_slice(&1_u16.to_le_bytes()); // Major version
+		shb.extend_from_slice(&0_u16.to_le_bytes()); // Minor version
+		shb.extend_from_slice(&(-1_i64).to_le_bytes()); // Unknown length
+		self.write_block(SECTION_HEADER_BLOCK, &shb)?;
+
+		let mut idb = Vec::with_capacity(8);
+		idb.extend_from_slice(&LINKTYPE_USB_LINUX_MMAPPED.to_le_bytes());
+		idb.extend_from_slice(&0_u16.to_le_bytes()); // Reserved
+		idb.extend_from_slice(&0_u32.to_le_bytes()); // No snapshot length
+		self.write_block(INTERFACE_DESCRIPTION_BLOCK, &idb)
+	}
+
+	/// Writes a block with the given type and body, padded to 32 bits
+	fn write_block(&mut self, kind: u32, body: &[u8]) -> std::io::Result<()> {
+		let padding = (4 - body.len() % 4) % 4;
+		let len = (12 + body.len() + padding) as u32;
+		self.writer.write_all(&kind.to_le_bytes())?;
+		self.writer.write_all(&len.to_le_bytes())?;
+		self.writer.write_all(body)?;
+		self.writer.write_all(&[0; 3][..padding])?;
+		self.writer.write_all(&len.to_le_bytes())
+	}
+
+	/// Writes a single usbmon event as a packet
+	fn write_event(
+		&mut self,
+		id: u64,
+		(bus, device): (u8, u8),
+		event: UsbEvent<'_>,
+	) {
+		if self.error.is_some() {
+			return;
+		}
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default();
+		let len = USBMON_HEADER_LEN + event.data.len();
+
+		let mut packet = Vec::with_capacity(len);
+		packet.extend_from_slice(&id.to_le_bytes());
+		packet.push(event.kind);
+		packet.push(event.xfer_type);
+		packet.push(event.endpoint);
+		packet.push(device);
+		packet.extend_from_slice(&u16::from(bus).to_le_bytes());
+		// The setup flag is 0 when there is a setup packet
+		packet.push(if event.setup.is_some() { 0 } else { b'-' });
+		// Likewise for the data flag, otherwise it says why there is none
+		packet.push(match (event.data.is_empty(), event.endpoint & 0x80) {
+			(false, _) => 0,
+			(true, 0) => b'>',
+			(true, _) => b'<',
+		});
+		packet.extend_from_slice(&(now.as_secs() as i64).to_le_bytes());
+		packet.extend_from_slice(&(now.subsec_micros() as i32).to_le_bytes());
+		packet.extend_from_slice(&event.status.to_le_bytes());
+		packet.extend_from_slice(&event.length.to_le_bytes());
+		packet.extend_from_slice(&(event.data.len() as u32).to_le_bytes());
+		packet.extend_from_slice(&event.setup.unwrap_or_default());
+		// Interval, start frame, transfer flags and isochronous descriptors
+		packet.extend_from_slice(&[0; 16]);
+		packet.extend_from_slice(event.data);
+
+		let micros = now.as_micros() as u64;
+		let mut epb = Vec::with_capacity(20 + len);
+		epb.extend_from_slice(&0_u32.to_le_bytes()); // Interface
+		epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+		epb.extend_from_slice(&(micros as u32).to_le_bytes());
+		epb.extend_from_slice(&(len as u32).to_le_bytes()); // Captured
+		epb.extend_from_slice(&(len as u32).to_le_bytes()); // Original
+		epb.extend_from_slice(&packet);
+		if let Err(err) = self.write_block(ENHANCED_PACKET_BLOCK, &epb) {
+			self.error = Some(err);
+		}
+	}
+
+	/// Takes the ID of the next transfer
+	fn take_id(&mut self) -> u64 {
+		self.next_id += 1;
+		self.next_id
+	}
+
+	/// Records a vendor request and its outcome
+	pub(crate) fn record_vendor_request(
+		&mut self,
+		bus_device: (u8, u8),
+		index: u16,
+		value: u16,
+		result: &Result<(), Error>,
+	) {
+		let id = self.take_id();
+		let mut setup = [0; 8];
+		setup[0] = rusb::request_type(
+			rusb::Direction::Out,
+			rusb::RequestType::Vendor,
+			rusb::Recipient::Device,
+		);
+		setup[1] = X52_VENDOR_REQUEST;
+		setup[2..4].copy_from_slice(&value.to_le_bytes());
+		setup[4..6].copy_from_slice(&index.to_le_bytes());
+		self.write_event(
+			id,
+			bus_device,
+			UsbEvent {
+				kind: b'S',
+				xfer_type: XFER_CONTROL,
+				endpoint: 0x00,
+				setup: Some(setup),
+				status: -EINPROGRESS,
+				length: 0,
+				data: &[],
+			},
+		);
+		self.write_event(
+			id,
+			bus_device,
+			UsbEvent {
+				kind: b'C',
+				xfer_type: XFER_CONTROL,
+				endpoint: 0x00,
+				setup: None,
+				status: status_of(result),
+				length: 0,
+				data: &[],
+			},
+		);
+	}
+
+	/// Records an input report read from the X52
+	pub(crate) fn record_report(
+		&mut self,
+		bus_device: (u8, u8),
+		report: &[u8],
+	) {
+		let id = self.take_id();
+		self.write_event(
+			id,
+			bus_device,
+			UsbEvent {
+				kind: b'C',
+				xfer_type: XFER_INTERRUPT,
+				endpoint: X52_REPORT_ENDPOINT,
+				setup: None,
+				status: 0,
+				length: report.len() as u32,
+				data: report,
+			},
+		);
+	}
+}
+
+/// The usbmon status of a finished transfer, 0 or a negative errno
+fn status_of(result: &Result<(), Error>) -> i32 {
+	let Err(err) = result else {
+		return 0;
+	};
+	match err.rusb_error() {
+		Some(rusb::Error::Timeout) => -ETIMEDOUT,
+		Some(rusb::Error::Pipe) => -EPIPE,
+		Some(rusb::Error::NoDevice) => -ENODEV,
+		_ => -EIO,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MockTransport, X52DeviceType, X52Driver};
+
+	/// Splits a pcapng file into (type, body) blocks
+	fn blocks(mut file: &[u8]) -> Vec<(u32, Vec<u8>)> {
+		let mut blocks = Vec::new();
+		while !file.is_empty() {
+			let kind = u32::from_le_bytes(file[0..4].try_into().unwrap());
+			let len = u32::from_le_bytes(file[4..8].try_into().unwrap());
+			let len = len as usize;
+			assert_eq!(file[len - 4..len], file[4..8]);
+			blocks.push((kind, file[8..len - 4].to_vec()));
+			file = &file[len..];
+		}
+		blocks
+	}
+
+	#[test]
+	fn records_requests_and_reports() {
+		let path = std::env::temp_dir()
+			.join(format!("rx52-capture-{}.pcapng", std::process::id()));
+		let mut driver =
+			X52Driver::new(MockTransport::new(), &X52DeviceType::X52Pro);
+		driver.start_capture(X52Capture::create(&path).unwrap());
+		driver.set_led_brightness(64).unwrap();
+		driver.transport().push_report(vec![1, 2, 3]);
+		driver
+			.read_report(&mut [0; 16], Default::default())
+			.unwrap();
+		driver.stop_capture().unwrap().finish().unwrap();
+
+		let file = std::fs::read(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+		let blocks = blocks(&file);
+		let kinds = blocks.iter().map(|x| x.0).collect::<Vec<_>>();
+		assert_eq!(kinds, vec![SECTION_HEADER_BLOCK, 1, 6, 6, 6]);
+		assert_eq!(blocks[1].1[0..2], LINKTYPE_USB_LINUX_MMAPPED.to_le_bytes());
+
+		// Skip the packet block's own fields to get at the usbmon packets
+		let packets =
+			blocks[2..].iter().map(|x| &x.1[20..]).collect::<Vec<_>>();
+		assert_eq!(packets[0][8], b'S');
+		assert_eq!(packets[0][40..48], [0x40, 0x91, 64, 0, 0xB2, 0, 0, 0]);
+		assert_eq!(packets[1][8], b'C');
+		assert_eq!(packets[0][0..8], packets[1][0..8]);
+		assert_eq!((packets[2][9], packets[2][10]), (XFER_INTERRUPT, 0x81));
+		// Followed by padding up to 32 bits
+		assert_eq!(packets[2][64..67], [1, 2, 3]);
+	}
+
+	#[test]
+	fn failed_requests_have_a_status() {
+		let err = Err(rusb::Error::Pipe.into());
+		assert_eq!(status_of(&err), -EPIPE);
+		assert_eq!(status_of(&Ok(())), 0);
+	}
+}
@@ -0,0 +1,206 @@
+//! High level commands which can be sent to an X52 as values
+
+use crate::{
+	protocol::{check_clock_offset, check_mfd_text},
+	Error, X52Capability, X52Clocks, X52ColoredLed, X52ColoredLedStatus,
+	X52DateFormat, X52Driver, X52MFDLine, X52OnOffLed, X52OnOffLedStatus,
+	X52Transport,
+};
+
+/// A single call to one of the setters of [`X52Driver`], as a value
+///
+/// Commands can be stored, queued and sent to a driver later with
+/// [`X52Driver::execute`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum X52Command {
+	/// See [`X52Driver::toggle_led_colored`]
+	SetColoredLed(X52ColoredLed, X52ColoredLedStatus),
+	/// See [`X52Driver::toggle_led_on_off`]
+	SetOnOffLed(X52OnOffLed, X52OnOffLedStatus),
+	/// See [`X52Driver::set_mfd_text`]
+	SetMfdText(X52MFDLine, String),
+	/// See [`X52Driver::clear_mfd_line`]
+	ClearMfdLine(X52MFDLine),
+	/// See [`X52Driver::set_led_brightness`]
+	SetLedBrightness(u8),
+	/// See [`X52Driver::set_mfd_brightness`]
+	SetMfdBrightness(u8),
+	/// See [`X52Driver::set_shift_status`]
+	SetShiftStatus(bool),
+	/// See [`X52Driver::set_blink_status`]
+	SetBlinkStatus(bool),
+	/// See [`X52Driver::set_clock_1`]
+	SetClock1 {
+		/// The hour to set
+		hour: u8,
+		/// The minute to set
+		minute: u8,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// See [`X52Driver::set_clock_2_offset`]
+	SetClock2Offset {
+		/// The offset from clock 1 in minutes
+		offset: i16,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// See [`X52Driver::set_clock_3_offset`]
+	SetClock3Offset {
+		/// The offset from clock 1 in minutes
+		offset: i16,
+		/// Whether to show the clock in 24 hour format
+		use_24h: bool,
+	},
+	/// See [`X52Driver::set_date`]
+	SetDate {
+		/// The day of the month
+		day: u8,
+		/// The month
+		month: u8,
+		/// The last two digits of the year
+		year: u8,
+		/// The order to show the date in
+		format: X52DateFormat,
+	},
+}
+
+/// The part of the X52 a command changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum CommandSlot {
+	ColoredLed(X52ColoredLed),
+	OnOffLed(X52OnOffLed),
+	MfdLine(X52MFDLine),
+	LedBrightness,
+	MfdBrightness,
+	ShiftStatus,
+	BlinkStatus,
+	Clock1,
+	Clock2,
+	Clock3,
+	Date,
+}
+
+impl CommandSlot {
+	/// Every part of the X52 which can be changed, in the order they are best
+	/// sent in
+	pub(crate) fn all() -> impl Iterator<Item = CommandSlot> {
+		X52ColoredLed::ALL
+			.into_iter()
+			.map(CommandSlot::ColoredLed)
+			.chain(X52OnOffLed::ALL.into_iter().map(CommandSlot::OnOffLed))
+			.chain(X52MFDLine::ALL.into_iter().map(CommandSlot::MfdLine))
+			.chain([
+				CommandSlot::LedBrightness,
+				CommandSlot::MfdBrightness,
+				CommandSlot::ShiftStatus,
+				CommandSlot::BlinkStatus,
+				CommandSlot::Clock1,
+				CommandSlot::Clock2,
+				CommandSlot::Clock3,
+				CommandSlot::Date,
+			])
+	}
+}
+
+impl X52Command {
+	/// The capability the X52 needs to run this command
+	pub fn capability(&self) -> X52Capability {
+		match self {
+			Self::SetColoredLed(..) => X52Capability::ColoredLeds,
+			Self::SetOnOffLed(..) => X52Capability::OnOffLeds,
+			Self::SetMfdText(..)
+			| Self::ClearMfdLine(_)
+			| Self::SetMfdBrightness(_) => X52Capability::Mfd,
+			Self::SetLedBrightness(_) => X52Capability::LedBrightness,
+			Self::SetShiftStatus(_) => X52Capability::Shift,
+			Self::SetBlinkStatus(_) => X52Capability::Blink,
+			Self::SetClock1 { .. }
+			| Self::SetClock2Offset { .. }
+			| Self::SetClock3Offset { .. }
+			| Self::SetDate { .. } => X52Capability::Clocks,
+		}
+	}
+
+	pub(crate) fn slot(&self) -> CommandSlot {
+		match self {
+			Self::SetColoredLed(led, _) => CommandSlot::ColoredLed(*led),
+			Self::SetOnOffLed(led, _) => CommandSlot::OnOffLed(*led),
+			Self::SetMfdText(line, _) => CommandSlot::MfdLine(*line),
+			Self::ClearMfdLine(line) => CommandSlot::MfdLine(*line),
+			Self::SetLedBrightness(_) => CommandSlot::LedBrightness,
+			Self::SetMfdBrightness(_) => CommandSlot::MfdBrightness,
+			Self::SetShiftStatus(_) => CommandSlot::ShiftStatus,
+			Self::SetBlinkStatus(_) => CommandSlot::BlinkStatus,
+			Self::SetClock1 { .. } => CommandSlot::Clock1,
+			Self::SetClock2Offset { .. } => CommandSlot::Clock2,
+			Self::SetClock3Offset { .. } => CommandSlot::Clock3,
+			Self::SetDate { .. } => CommandSlot::Date,
+		}
+	}
+
+	/// Returns true if running this command makes running `other` pointless
+	///
+	/// This is the case when both commands change the same part of the X52,
+	/// for example two colours for the same LED, or a clear and a write to the
+	/// same MFD line.
+	pub fn supersedes(&self, other: &X52Command) -> bool {
+		self.slot() == other.slot()
+	}
+
+	/// Checks the arguments of the command without sending it anywhere
+	///
+	/// Returns the same error the matching [`X52Driver`] method would for
+	/// text which doesn't fit on the MFD or clock offsets which are too big.
+	pub fn validate(&self) -> Result<(), Error> {
+		match self {
+			Self::SetMfdText(_, text) => check_mfd_text(text),
+			Self::SetClock2Offset { offset, .. } => {
+				check_clock_offset(&X52Clocks::Clock2, *offset)
+			}
+			Self::SetClock3Offset { offset, .. } => {
+				check_clock_offset(&X52Clocks::Clock3, *offset)
+			}
+			_ => Ok(()),
+		}
+	}
+}
+
+impl<T: X52Transport> X52Driver<T> {
+	/// Runs a single [`X52Command`]
+	pub fn execute(&self, command: &X52Command) -> Result<(), Error> {
+		match command {
+			X52Command::SetColoredLed(led, status) => {
+				self.toggle_led_colored(led, status)
+			}
+			X52Command::SetOnOffLed(led, status) => {
+				self.toggle_led_on_off(led, status)
+			}
+			X52Command::SetMfdText(line, text) => {
+				self.set_mfd_text(line, text.clone())
+			}
+			X52Command::ClearMfdLine(line) => self.clear_mfd_line(line),
+			X52Command::SetLedBrightness(x) => self.set_led_brightness(*x),
+			X52Command::SetMfdBrightness(x) => self.set_mfd_brightness(*x),
+			X52Command::SetShiftStatus(x) => self.set_shift_status(*x),
+			X52Command::SetBlinkStatus(x) => self.set_blink_status(*x),
+			X52Command::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			} => self.set_clock_1(*hour, *minute, *use_24h),
+			X52Command::SetClock2Offset { offset, use_24h } => {
+				self.set_clock_2_offset(*offset, *use_24h)
+			}
+			X52Command::SetClock3Offset { offset, use_24h } => {
+				self.set_clock_3_offset(*offset, *use_24h)
+			}
+			X52Command::SetDate {
+				day,
+				month,
+				year,
+				format,
+			} => self.set_date(*day, *month, *year, *format),
+		}
+	}
+}
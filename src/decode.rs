@@ -0,0 +1,653 @@
+//! Decoding the vendor requests sent to X52s from captures of USB traffic
+
+use crate::capture::{
+	BYTE_ORDER_MAGIC, ENHANCED_PACKET_BLOCK, INTERFACE_DESCRIPTION_BLOCK,
+	LINKTYPE_USB_LINUX_MMAPPED, SECTION_HEADER_BLOCK, USBMON_HEADER_LEN,
+};
+use crate::{
+	find_descriptor_by_ids, Error, ErrorId, X52DeviceType, X52VendorCommand,
+};
+use rx52_core::X52_VENDOR_REQUEST;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Read};
+use std::time::Duration;
+
+/// LINKTYPE_USB_LINUX, usbmon without the fields added for mmap
+const LINKTYPE_USB_LINUX: u16 = 189;
+/// The size of the usbmon header of LINKTYPE_USB_LINUX
+const USB_LINUX_HEADER_LEN: usize = 48;
+/// LINKTYPE_USBPCAP, the format of USBPcap on Windows
+const LINKTYPE_USBPCAP: u16 = 249;
+/// The pcapng block of packets without a timestamp
+const SIMPLE_PACKET_BLOCK: u32 = 3;
+/// The pcapng option giving the resolution of timestamps
+const IF_TSRESOL: u16 = 9;
+/// The magic numbers of pcap files with microsecond and nanosecond timestamps
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+/// The request type of a vendor request to the device, as sent to the X52
+const VENDOR_OUT_REQUEST_TYPE: u8 = 0x40;
+/// The request type, request and descriptor type of a request for a device
+/// descriptor
+const GET_DEVICE_DESCRIPTOR: [u8; 2] = [0x80, 0x06];
+const DEVICE_DESCRIPTOR_TYPE: u8 = 1;
+
+/// A vendor request found in a capture of USB traffic
+///
+/// Displays as the bus and device it was sent to, followed by what the
+/// request does, like `001:004 MFD line 2 chars 'AB'`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X52DecodedRequest {
+	timestamp: Duration,
+	bus: u16,
+	device: u16,
+	index: u16,
+	value: u16,
+	x52_type: Option<&'static X52DeviceType>,
+}
+
+impl X52DecodedRequest {
+	/// When the request was sent, as recorded in the capture
+	pub fn timestamp(&self) -> Duration {
+		self.timestamp
+	}
+
+	/// The bus the request was sent on
+	pub fn bus(&self) -> u16 {
+		self.bus
+	}
+
+	/// The address of the device the request was sent to
+	pub fn device(&self) -> u16 {
+		self.device
+	}
+
+	/// The index (command) of the request
+	pub fn index(&self) -> u16 {
+		self.index
+	}
+
+	/// The value of the request
+	pub fn value(&self) -> u16 {
+		self.value
+	}
+
+	/// The type of X52 the request was sent to
+	///
+	/// This is only known if the capture includes the device being
+	/// enumerated, otherwise it is [`None`].
+	pub fn x52_type(&self) -> Option<&'static X52DeviceType> {
+		self.x52_type
+	}
+
+	/// The request as a command from the catalogue, if it is in there
+	pub fn command(&self) -> Option<X52VendorCommand> {
+		X52VendorCommand::decode(self.index, self.value)
+	}
+}
+
+impl Display for X52DecodedRequest {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(fmt, "{:03}:{:03} ", self.bus, self.device)?;
+		match self.command() {
+			Some(command) => write!(fmt, "{command}"),
+			None => write!(
+				fmt,
+				"unknown request {:#04X} {:#06X}",
+				self.index, self.value
+			),
+		}
+	}
+}
+
+/// Creates the error for a capture which could not be read
+fn invalid(msg: String) -> Error {
+	Error::new(ErrorId::InvalidCapture, msg)
+}
+
+/// A single transfer being submitted or completed, in any capture format
+struct UsbEvent<'a> {
+	/// Pairs the submission of a transfer with its completion
+	id: u64,
+	bus: u16,
+	device: u16,
+	timestamp: Duration,
+	submit: bool,
+	setup: Option<[u8; 8]>,
+	data: &'a [u8],
+}
+
+/// Picks the vendor requests sent to X52s out of a stream of events
+#[derive(Default)]
+struct Decoder {
+	/// Device descriptor requests waiting for their response
+	pending: HashMap<u64, (u16, u16)>,
+	/// The devices whose descriptor has been seen, and which X52 they are
+	devices: HashMap<(u16, u16), Option<&'static X52DeviceType>>,
+	requests: Vec<X52DecodedRequest>,
+}
+
+impl Decoder {
+	fn handle(&mut self, event: UsbEvent<'_>) {
+		let key = (event.bus, event.device);
+		match event.setup {
+			Some(setup) if event.submit => {
+				if setup[..2] == GET_DEVICE_DESCRIPTOR
+					&& setup[3] == DEVICE_DESCRIPTOR_TYPE
+				{
+					self.pending.insert(event.id, key);
+				} else if setup[0] == VENDOR_OUT_REQUEST_TYPE
+					&& setup[1] == X52_VENDOR_REQUEST
+				{
+					self.requests.push(X52DecodedRequest {
+						timestamp: event.timestamp,
+						bus: event.bus,
+						device: event.device,
+						value: u16::from_le_bytes([setup[2], setup[3]]),
+						index: u16::from_le_bytes([setup[4], setup[5]]),
+						x52_type: None,
+					});
+				}
+			}
+			// The response to a device descriptor request, with the vendor and
+			// product IDs at bytes 8 to 11
+			_ if !event.submit
+				&& event.data.len() >= 12
+				&& self.pending.remove(&event.id).is_some() =>
+			{
+				let ids = &event.data[8..12];
+				let vendor = u16::from_le_bytes([ids[0], ids[1]]);
+				let product = u16::from_le_bytes([ids[2], ids[3]]);
+				self.devices.insert(
+					key,
+					find_descriptor_by_ids(vendor, product)
+						.map(|x| x.x52_type()),
+				);
+			}
+			_ => {}
+		}
+	}
+
+	/// Drops the requests to devices known not to be X52s
+	fn finish(self) -> Vec<X52DecodedRequest> {
+		self.requests
+			.into_iter()
+			.filter_map(|mut request| {
+				match self.devices.get(&(request.bus, request.device)) {
+					Some(None) => None,
+					Some(Some(x52_type)) => {
+						request.x52_type = Some(x52_type);
+						Some(request)
+					}
+					None => Some(request),
+				}
+			})
+			.collect()
+	}
+}
+
+/// Parses the bytes of a usbmon text data field, like `12010002 00000008`
+fn parse_hex_words(words: &[&str]) -> Option<Vec<u8>> {
+	let mut data = Vec::new();
+	for word in words {
+		if word.len() % 2 != 0 {
+			return None;
+		}
+		for i in (0..word.len()).step_by(2) {
+			data.push(u8::from_str_radix(word.get(i..i + 2)?, 16).ok()?);
+		}
+	}
+	Some(data)
+}
+
+/// Parses a line of a usbmon text dump into its event, and the data of the
+/// event
+fn parse_usbmon_line(line: &str) -> Option<(UsbEvent<'static>, Vec<u8>)> {
+	let words = line.split_whitespace().collect::<Vec<_>>();
+	let id = u64::from_str_radix(words.first()?, 16).ok()?;
+	let timestamp = Duration::from_micros(words.get(1)?.parse().ok()?);
+	let submit = match *words.get(2)? {
+		"S" => true,
+		"C" | "E" => false,
+		_ => return None,
+	};
+	// Either Co:1:004:0, or Co:004:0 in the older format without the bus
+	let address = words.get(3)?.split(':').collect::<Vec<_>>();
+	let (bus, device) = match address[..] {
+		[_, bus, device, _] => (bus.parse().ok()?, device.parse().ok()?),
+		[_, device, _] => (0, device.parse().ok()?),
+		_ => return None,
+	};
+	let mut rest = &words[4..];
+	let mut setup = None;
+	if rest.first() == Some(&"s") {
+		let fields = rest.get(1..6)?;
+		let mut packet = [0; 8];
+		packet[0] = u8::from_str_radix(fields[0], 16).ok()?;
+		packet[1] = u8::from_str_radix(fields[1], 16).ok()?;
+		for (i, field) in fields[2..].iter().enumerate() {
+			let field = u16::from_str_radix(field, 16).ok()?;
+			packet[2 + i * 2..4 + i * 2].copy_from_slice(&field.to_le_bytes());
+		}
+		setup = Some(packet);
+		rest = &rest[6..];
+	} else {
+		// The status of the transfer
+		rest = rest.get(1..)?;
+	}
+	// The length, then the data if there is any
+	let data = match rest.get(1..) {
+		Some([tag, words @ ..]) if *tag == "=" => parse_hex_words(words)?,
+		_ => Vec::new(),
+	};
+	Some((
+		UsbEvent {
+			id,
+			bus,
+			device,
+			timestamp,
+			submit,
+			setup,
+			data: &[],
+		},
+		data,
+	))
+}
+
+/// Decodes the vendor requests sent to X52s from a usbmon text dump
+///
+/// The dump is what reading `/sys/kernel/debug/usb/usbmon/<bus>u` gives,
+/// in either the current format or the older one without bus numbers.
+///
+/// A capture doesn't say which device is which, unless it includes the
+/// devices being enumerated. Requests to devices which the capture shows
+/// are not X52s are left out, and every request which could be for an X52 is
+/// kept otherwise.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::BufReader;
+///
+/// let dump = BufReader::new(std::fs::File::open("x52.usbmon").unwrap());
+/// for request in rx52::decode_usbmon_text(dump).unwrap() {
+///     println!("{request}");
+/// }
+/// ```
+pub fn decode_usbmon_text<R: BufRead>(
+	reader: R,
+) -> Result<Vec<X52DecodedRequest>, Error> {
+	let mut decoder = Decoder::default();
+	for (number, line) in reader.lines().enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+		let (mut event, data) = parse_usbmon_line(&line).ok_or_else(|| {
+			invalid(format!("Line {} is not a usbmon event", number + 1))
+		})?;
+		event.data = &data;
+		decoder.handle(event);
+	}
+	Ok(decoder.finish())
+}
+
+/// Reads integers in the byte order of a capture
+#[derive(Debug, Clone, Copy)]
+struct Endian {
+	big: bool,
+}
+
+impl Endian {
+	fn u16(self, bytes: &[u8], at: usize) -> Option<u16> {
+		let bytes = bytes.get(at..at + 2)?.try_into().ok()?;
+		Some(match self.big {
+			true => u16::from_be_bytes(bytes),
+			false => u16::from_le_bytes(bytes),
+		})
+	}
+
+	fn u32(self, bytes: &[u8], at: usize) -> Option<u32> {
+		let bytes = bytes.get(at..at + 4)?.try_into().ok()?;
+		Some(match self.big {
+			true => u32::from_be_bytes(bytes),
+			false => u32::from_le_bytes(bytes),
+		})
+	}
+
+	fn u64(self, bytes: &[u8], at: usize) -> Option<u64> {
+		let bytes = bytes.get(at..at + 8)?.try_into().ok()?;
+		Some(match self.big {
+			true => u64::from_be_bytes(bytes),
+			false => u64::from_le_bytes(bytes),
+		})
+	}
+}
+
+/// Parses a single packet with the given link type
+///
+/// usbmon headers are in the byte order of the machine which made the
+/// capture, which is the byte order of the file. USBPcap only runs on
+/// little endian Windows.
+fn parse_packet(
+	linktype: u16,
+	endian: Endian,
+	timestamp: Duration,
+	packet: &[u8],
+) -> Option<UsbEvent<'_>> {
+	match linktype {
+		LINKTYPE_USB_LINUX | LINKTYPE_USB_LINUX_MMAPPED => {
+			let header_len = match linktype {
+				LINKTYPE_USB_LINUX => USB_LINUX_HEADER_LEN,
+				_ => USBMON_HEADER_LEN,
+			};
+			let captured = endian.u32(packet, 36)? as usize;
+			let data = packet.get(header_len..)?;
+			Some(UsbEvent {
+				id: endian.u64(packet, 0)?,
+				bus: endian.u16(packet, 12)?,
+				device: *packet.get(11)? as u16,
+				timestamp,
+				submit: *packet.get(8)? == b'S',
+				setup: match packet.get(14)? {
+					0 => Some(packet.get(40..48)?.try_into().ok()?),
+					_ => None,
+				},
+				data: &data[..captured.min(data.len())],
+			})
+		}
+		LINKTYPE_USBPCAP => {
+			let endian = Endian { big: false };
+			let header_len = endian.u16(packet, 0)? as usize;
+			let data = packet.get(header_len..)?;
+			// Control transfers have a stage after the common header, where
+			// 0 is the setup stage
+			let is_setup = *packet.get(22)? == 2 && *packet.get(27)? == 0;
+			Some(UsbEvent {
+				id: endian.u64(packet, 2)?,
+				bus: endian.u16(packet, 17)?,
+				device: endian.u16(packet, 19)?,
+				timestamp,
+				// Bit 0 of the info field is set on the way back from the
+				// device
+				submit: *packet.get(16)? & 1 == 0,
+				setup: match is_setup {
+					true => Some(data.get(..8)?.try_into().ok()?),
+					false => None,
+				},
+				data,
+			})
+		}
+		_ => None,
+	}
+}
+
+/// Whether packets with the given link type can be decoded
+fn is_usb(linktype: u16) -> bool {
+	matches!(
+		linktype,
+		LINKTYPE_USB_LINUX | LINKTYPE_USB_LINUX_MMAPPED | LINKTYPE_USBPCAP
+	)
+}
+
+/// Converts a timestamp in the given units per second to a duration
+fn to_duration(timestamp: u64, per_second: u64) -> Duration {
+	let fraction =
+		(timestamp % per_second) as u128 * 1_000_000_000 / per_second as u128;
+	Duration::from_secs(timestamp / per_second)
+		+ Duration::from_nanos(fraction as u64)
+}
+
+/// Decodes a pcap file
+fn decode_classic(
+	file: &[u8],
+	endian: Endian,
+	per_second: u64,
+) -> Result<Vec<X52DecodedRequest>, Error> {
+	let truncated = || invalid("The pcap file is truncated".to_string());
+	let linktype = endian.u32(file, 20).ok_or_else(truncated)? as u16;
+	if !is_usb(linktype) {
+		return Err(invalid(format!(
+			"The capture has link type {linktype}, which is not USB"
+		)));
+	}
+	let mut decoder = Decoder::default();
+	let mut at = 24;
+	while at < file.len() {
+		let seconds = endian.u32(file, at).ok_or_else(truncated)?;
+		let fraction = endian.u32(file, at + 4).ok_or_else(truncated)?;
+		let len = endian.u32(file, at + 8).ok_or_else(truncated)? as usize;
+		let packet = file.get(at + 16..at + 16 + len).ok_or_else(truncated)?;
+		let timestamp = Duration::from_secs(seconds as u64)
+			+ to_duration(fraction as u64, per_second);
+		if let Some(event) = parse_packet(linktype, endian, timestamp, packet) {
+			decoder.handle(event);
+		}
+		at += 16 + len;
+	}
+	Ok(decoder.finish())
+}
+
+/// The timestamp units per second given by an `if_tsresol` option
+fn units_per_second(tsresol: u8) -> u64 {
+	match tsresol {
+		x if x & 0x80 != 0 => 1 << (x & 0x7F).min(63),
+		x => 10_u64.pow(x.min(19) as u32),
+	}
+}
+
+/// The link type and timestamp units per second of a pcapng interface
+fn parse_interface(endian: Endian, body: &[u8]) -> Option<(u16, u64)> {
+	let linktype = endian.u16(body, 0)?;
+	let mut per_second = 1_000_000;
+	let mut at = 8;
+	while let (Some(code), Some(len)) =
+		(endian.u16(body, at), endian.u16(body, at + 2))
+	{
+		if code == 0 {
+			break;
+		}
+		if code == IF_TSRESOL {
+			per_second = units_per_second(*body.get(at + 4)?);
+		}
+		at += 4 + (len as usize).div_ceil(4) * 4;
+	}
+	Some((linktype, per_second))
+}
+
+/// Decodes a pcapng file
+fn decode_ng(file: &[u8]) -> Result<Vec<X52DecodedRequest>, Error> {
+	let truncated = || invalid("The pcapng file is truncated".to_string());
+	let mut decoder = Decoder::default();
+	let mut endian = Endian { big: false };
+	let mut interfaces = Vec::new();
+	let mut at = 0;
+	while at < file.len() {
+		let kind = endian.u32(file, at).ok_or_else(truncated)?;
+		if kind == SECTION_HEADER_BLOCK {
+			// Every section says its own byte order
+			let magic = file.get(at + 8..at + 12).ok_or_else(truncated)?;
+			endian.big = magic == BYTE_ORDER_MAGIC.to_be_bytes();
+			interfaces.clear();
+		}
+		let len = endian.u32(file, at + 4).ok_or_else(truncated)? as usize;
+		if len < 12 {
+			return Err(invalid(format!(
+				"The pcapng block at byte {at} is too short"
+			)));
+		}
+		let body = file.get(at + 8..at + len - 4).ok_or_else(truncated)?;
+		at += len;
+		let (interface, timestamp, packet) = match kind {
+			INTERFACE_DESCRIPTION_BLOCK => {
+				interfaces
+					.push(parse_interface(endian, body).ok_or_else(truncated)?);
+				continue;
+			}
+			ENHANCED_PACKET_BLOCK => {
+				let interface = endian.u32(body, 0).ok_or_else(truncated)?;
+				let high = endian.u32(body, 4).ok_or_else(truncated)?;
+				let low = endian.u32(body, 8).ok_or_else(truncated)?;
+				let len = endian.u32(body, 12).ok_or_else(truncated)?;
+				let packet =
+					body.get(20..20 + len as usize).ok_or_else(truncated)?;
+				(interface, Some((high as u64) << 32 | low as u64), packet)
+			}
+			SIMPLE_PACKET_BLOCK => {
+				(0, None, body.get(4..).ok_or_else(truncated)?)
+			}
+			_ => continue,
+		};
+		let Some(&(linktype, per_second)) = interfaces.get(interface as usize)
+		else {
+			return Err(invalid(format!(
+				"A packet refers to interface {interface}, which isn't \
+				 described"
+			)));
+		};
+		let timestamp = timestamp
+			.map(|x| to_duration(x, per_second))
+			.unwrap_or_default();
+		if let Some(event) = parse_packet(linktype, endian, timestamp, packet) {
+			decoder.handle(event);
+		}
+	}
+	Ok(decoder.finish())
+}
+
+/// Decodes the vendor requests sent to X52s from a pcap or pcapng file
+///
+/// Captures made with usbmon on Linux (including the ones made by
+/// [`X52Capture`](crate::X52Capture)) and with USBPcap on Windows are
+/// understood. Packets on other interfaces of a pcapng file are skipped.
+///
+/// Like [`decode_usbmon_text`], requests to devices which the capture shows
+/// are not X52s are left out.
+///
+/// # Examples
+///
+/// ```no_run
+/// let capture = std::fs::File::open("x52.pcapng").unwrap();
+/// for request in rx52::decode_pcap(capture).unwrap() {
+///     println!("{:?} {request}", request.timestamp());
+/// }
+/// ```
+pub fn decode_pcap<R: Read>(
+	mut reader: R,
+) -> Result<Vec<X52DecodedRequest>, Error> {
+	let mut file = Vec::new();
+	reader.read_to_end(&mut file)?;
+	let magic = file
+		.get(..4)
+		.ok_or_else(|| invalid("The capture is empty".to_string()))?;
+	let magic = <[u8; 4]>::try_from(magic).unwrap();
+	match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+		(SECTION_HEADER_BLOCK, _) => decode_ng(&file),
+		(PCAP_MAGIC_MICROS, _) => {
+			decode_classic(&file, Endian { big: false }, 1_000_000)
+		}
+		(PCAP_MAGIC_NANOS, _) => {
+			decode_classic(&file, Endian { big: false }, 1_000_000_000)
+		}
+		(_, PCAP_MAGIC_MICROS) => {
+			decode_classic(&file, Endian { big: true }, 1_000_000)
+		}
+		(_, PCAP_MAGIC_NANOS) => {
+			decode_classic(&file, Endian { big: true }, 1_000_000_000)
+		}
+		_ => Err(invalid("The capture is not a pcap or pcapng file".into())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MockTransport, X52Capture, X52Driver, X52MFDLine};
+
+	#[test]
+	fn usbmon_text_filters_by_descriptor() {
+		let dump = "\
+ffff8a1 100 S Ci:1:004:0 s 80 06 0100 0000 0012 18 <
+ffff8a1 200 C Ci:1:004:0 0 18 = 12010002 00000008 a3066207 00010102 0001
+ffff8a2 300 S Co:1:004:0 s 40 91 1300 00b8 0000 0
+ffff8a2 400 C Co:1:004:0 0 0
+ffff8a3 500 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <
+ffff8a3 600 C Ci:1:005:0 0 18 = 12010002 00000008 6d0400c0 00010102 0001
+ffff8a4 700 S Co:1:005:0 s 40 91 1300 00b8 0000 0
+
+ffff8a5 800 S Co:006:0 s 40 91 843c 00c1 0000 0
+";
+		let requests = decode_usbmon_text(dump.as_bytes()).unwrap();
+		assert_eq!(requests.len(), 2);
+		assert_eq!(requests[0].to_string(), "001:004 LED Clutch green off");
+		assert_eq!(requests[0].x52_type(), Some(&X52DeviceType::X52Pro));
+		assert_eq!(requests[0].timestamp(), Duration::from_micros(300));
+		assert_eq!(
+			requests[1].to_string(),
+			"000:006 clock 2 offset -60 min, 24h"
+		);
+		assert_eq!(requests[1].x52_type(), None);
+	}
+
+	#[test]
+	fn usbmon_text_rejects_garbage() {
+		let err = decode_usbmon_text("not usbmon".as_bytes()).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::InvalidCapture));
+	}
+
+	#[test]
+	fn decodes_recorded_captures() {
+		let path = std::env::temp_dir()
+			.join(format!("rx52-decode-{}.pcapng", std::process::id()));
+		let mut driver =
+			X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		driver.start_capture(X52Capture::create(&path).unwrap());
+		driver.set_clock_3_offset(-60, true).unwrap();
+		driver
+			.set_mfd_text(&X52MFDLine::Line2, "ABCD".to_string())
+			.unwrap();
+		driver.stop_capture().unwrap().finish().unwrap();
+
+		let file = std::fs::File::open(&path).unwrap();
+		let requests = decode_pcap(file).unwrap();
+		let _ = std::fs::remove_file(&path);
+		let sent = driver.transport().requests();
+		assert_eq!(
+			requests
+				.iter()
+				.map(|x| (x.index(), x.value()))
+				.collect::<Vec<_>>(),
+			sent
+		);
+		assert!(requests.iter().any(|x| x.to_string().contains("'AB'")));
+	}
+
+	#[test]
+	fn decodes_classic_pcap() {
+		let mut packet = vec![0; USB_LINUX_HEADER_LEN];
+		packet[8] = b'S';
+		packet[11] = 4;
+		packet[12] = 1;
+		packet[40..48].copy_from_slice(&[0x40, 0x91, 64, 0, 0xB2, 0, 0, 0]);
+		let mut file = Vec::new();
+		file.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+		file.extend_from_slice(&[2, 0, 4, 0]);
+		file.extend_from_slice(&[0; 12]);
+		file.extend_from_slice(&(LINKTYPE_USB_LINUX as u32).to_le_bytes());
+		file.extend_from_slice(&5_u32.to_le_bytes());
+		file.extend_from_slice(&250_u32.to_le_bytes());
+		file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+		file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+		file.extend_from_slice(&packet);
+
+		let requests = decode_pcap(file.as_slice()).unwrap();
+		assert_eq!(requests.len(), 1);
+		assert_eq!(requests[0].to_string(), "001:004 LED brightness 64");
+		assert_eq!(
+			requests[0].timestamp(),
+			Duration::from_secs(5) + Duration::from_micros(250)
+		);
+	}
+}
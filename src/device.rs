@@ -0,0 +1,317 @@
+//! Finding the X52s attached to the system
+
+use crate::{
+	find_descriptor, Error, ErrorId, UsbTransport, X52Descriptor,
+	X52DeviceInfo, X52DeviceType, X52Driver,
+};
+use rusb::{Context, Device, DeviceHandle, UsbContext};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// An identifier for an X52 which stays the same across replugs and reboots
+///
+/// Bus addresses change every time a device is plugged in, so this instead
+/// uses the path of USB ports leading to the X52, and its serial number if it
+/// has one. As long as an X52 stays plugged into the same port, or has a
+/// serial number, it keeps the same ID.
+///
+/// IDs are written like Linux names USB devices in sysfs, `<bus>-<ports>`,
+/// with the serial number appended after a `#` if there is one.
+///
+/// # Examples
+///
+/// ```
+/// let id: rx52::X52DeviceId = "1-2.4".parse().unwrap();
+/// assert_eq!(id.bus(), 1);
+/// assert_eq!(id.port_path(), &[2, 4]);
+/// assert_eq!(id.to_string(), "1-2.4");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct X52DeviceId {
+	bus: u8,
+	port_path: Vec<u8>,
+	serial: Option<String>,
+}
+
+impl X52DeviceId {
+	/// Creates an ID from a bus, port path and optional serial number
+	pub fn new(bus: u8, port_path: Vec<u8>, serial: Option<String>) -> Self {
+		Self {
+			bus,
+			port_path,
+			serial,
+		}
+	}
+
+	/// The number of the bus the X52 is on
+	pub fn bus(&self) -> u8 {
+		self.bus
+	}
+
+	/// The ports leading from the root hub to the X52, outermost first
+	pub fn port_path(&self) -> &[u8] {
+		&self.port_path
+	}
+
+	/// The serial number of the X52, if it has one
+	pub fn serial(&self) -> Option<&str> {
+		self.serial.as_deref()
+	}
+
+	/// Returns true if this ID refers to the device at the given location
+	///
+	/// When both sides know a serial number only the serial numbers are
+	/// compared, so the X52 can be found again on a different port.
+	pub fn matches(
+		&self,
+		bus: u8,
+		port_path: &[u8],
+		serial: Option<&str>,
+	) -> bool {
+		match (self.serial(), serial) {
+			(Some(x), Some(y)) => x == y,
+			_ => self.bus == bus && self.port_path == port_path,
+		}
+	}
+}
+
+impl Display for X52DeviceId {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		write!(fmt, "{}-", self.bus)?;
+		for (i, port) in self.port_path.iter().enumerate() {
+			if i != 0 {
+				write!(fmt, ".")?;
+			}
+			write!(fmt, "{port}")?;
+		}
+		if let Some(serial) = &self.serial {
+			write!(fmt, "#{serial}")?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for X52DeviceId {
+	type Err = Error;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		let invalid = || {
+			Error::new(
+				ErrorId::InvalidDeviceId,
+				format!("\"{string}\" is not a valid X52 device ID"),
+			)
+		};
+		let (location, serial) = match string.split_once('#') {
+			Some((location, serial)) => (location, Some(serial.to_string())),
+			None => (string, None),
+		};
+		let (bus, ports) = location.split_once('-').ok_or_else(invalid)?;
+		// No ports is what Display writes when the port path is unknown
+		let port_path = match ports {
+			"" => Vec::new(),
+			_ => ports
+				.split('.')
+				.map(|x| x.parse().map_err(|_| invalid()))
+				.collect::<Result<_, _>>()?,
+		};
+		Ok(Self {
+			bus: bus.parse().map_err(|_| invalid())?,
+			port_path,
+			serial,
+		})
+	}
+}
+
+/// Reads the serial number of a device, if it has one
+pub(crate) fn read_serial(
+	device: &Device<Context>,
+	handle: &DeviceHandle<Context>,
+) -> Option<String> {
+	let descriptor = device.device_descriptor().ok()?;
+	handle.read_serial_number_string_ascii(&descriptor).ok()
+}
+
+/// An X52 attached to the system which has not been opened yet
+///
+/// Returned by [`get_devices`]. Listing devices does not need permission to
+/// open them, so entries can be shown to a user before picking one to
+/// [`open`](X52DeviceEntry::open).
+pub struct X52DeviceEntry {
+	device: Device<Context>,
+	descriptor: &'static X52Descriptor,
+	port_path: Vec<u8>,
+}
+
+impl X52DeviceEntry {
+	/// Creates an entry for `device` if it is an X52
+	pub(crate) fn from_device(device: Device<Context>) -> Option<Self> {
+		let descriptor = find_descriptor(&device.device_descriptor().ok()?)?;
+		Some(Self {
+			// Devices plugged straight into the root hub have no ports
+			port_path: device.port_numbers().unwrap_or_default(),
+			device,
+			descriptor,
+		})
+	}
+
+	/// The number of the bus the X52 is on
+	pub fn bus(&self) -> u8 {
+		self.device.bus_number()
+	}
+
+	/// The address of the X52 on its bus
+	///
+	/// This changes every time the X52 is plugged in.
+	pub fn address(&self) -> u8 {
+		self.device.address()
+	}
+
+	/// The ports leading from the root hub to the X52, outermost first
+	pub fn port_path(&self) -> &[u8] {
+		&self.port_path
+	}
+
+	/// The descriptor the X52 matched
+	pub fn descriptor(&self) -> &'static X52Descriptor {
+		self.descriptor
+	}
+
+	/// The type of X52 this entry refers to
+	pub fn x52_type(&self) -> &'static X52DeviceType {
+		self.descriptor.x52_type()
+	}
+
+	/// The underlying USB device
+	pub fn device(&self) -> &Device<Context> {
+		&self.device
+	}
+
+	/// The stable ID of the X52
+	///
+	/// Reading the serial number needs the device to be opened. If that
+	/// fails, for example because of missing permissions, the ID is made from
+	/// the port path alone.
+	pub fn id(&self) -> X52DeviceId {
+		X52DeviceId::new(
+			self.bus(),
+			self.port_path.clone(),
+			self.device
+				.open()
+				.ok()
+				.and_then(|handle| read_serial(&self.device, &handle)),
+		)
+	}
+
+	/// Everything USB says about the X52, see [`X52Driver::device_info`]
+	///
+	/// Like [`X52DeviceEntry::id`], this opens the device to read its
+	/// strings, and leaves them out if that fails.
+	pub fn info(&self) -> Result<X52DeviceInfo, Error> {
+		let handle = self.device.open().ok();
+		X52DeviceInfo::read(&self.device, handle.as_ref())
+	}
+
+	/// Opens the X52, creating a driver for it
+	pub fn open(&self) -> Result<X52Driver, Error> {
+		Ok(X52Driver::new(
+			UsbTransport::open(self.device.clone())?,
+			self.x52_type(),
+		))
+	}
+}
+
+impl Display for X52DeviceEntry {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		write!(
+			fmt,
+			"Bus {:03} Device {:03}: {}",
+			self.bus(),
+			self.address(),
+			self.descriptor.description()
+		)
+	}
+}
+
+/// Lists the X52s attached to the given context
+pub(crate) fn get_devices_in(
+	ctx: &Context,
+) -> Result<Vec<X52DeviceEntry>, Error> {
+	Ok(ctx
+		.devices()?
+		.iter()
+		.filter_map(X52DeviceEntry::from_device)
+		.collect())
+}
+
+/// Finds the X52 with the given ID among the devices attached to `ctx`
+pub(crate) fn find_device_by_id(
+	ctx: &Context,
+	id: &X52DeviceId,
+) -> Result<X52DeviceEntry, Error> {
+	get_devices_in(ctx)?
+		.into_iter()
+		.find(|x| {
+			// Only bother opening devices to read the serial if it matters
+			let serial = match id.serial() {
+				Some(_) => x.id().serial,
+				None => None,
+			};
+			id.matches(x.bus(), x.port_path(), serial.as_deref())
+		})
+		.ok_or(Error::new(
+			ErrorId::DeviceIdNotFound,
+			format!("No X52 found with the ID {id}"),
+		))
+}
+
+/// Lists every X52 attached to the system
+///
+/// # Examples
+///
+/// ```no_run
+/// for device in rx52::get_devices().unwrap() {
+///     println!("{device}, ports {:?}", device.port_path());
+/// }
+/// ```
+pub fn get_devices() -> Result<Vec<X52DeviceEntry>, Error> {
+	get_devices_in(&Context::new()?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn id_round_trip() {
+		for string in ["1-2", "3-1.4.2", "2-7#ABC123", "1-", "4-#XYZ"] {
+			let id: X52DeviceId = string.parse().unwrap();
+			assert_eq!(id.to_string(), string);
+		}
+		let id = X52DeviceId::new(1, Vec::new(), None);
+		assert_eq!(id.to_string().parse::<X52DeviceId>().unwrap(), id);
+	}
+
+	#[test]
+	fn id_invalid() {
+		for string in ["", "1", "a-2", "1-2..3", "1-.2", "300-1"] {
+			let err = string.parse::<X52DeviceId>().unwrap_err();
+			assert_eq!(err.id(), Some(ErrorId::InvalidDeviceId));
+		}
+	}
+
+	#[test]
+	fn id_matches_port_path() {
+		let id = X52DeviceId::new(1, vec![2, 3], None);
+		assert!(id.matches(1, &[2, 3], Some("ABC")));
+		assert!(!id.matches(1, &[2, 4], None));
+		assert!(!id.matches(2, &[2, 3], None));
+	}
+
+	#[test]
+	fn id_prefers_serial() {
+		let id = X52DeviceId::new(1, vec![2, 3], Some("ABC".to_string()));
+		assert!(id.matches(4, &[1], Some("ABC")));
+		assert!(!id.matches(1, &[2, 3], Some("DEF")));
+		assert!(id.matches(1, &[2, 3], None));
+	}
+}
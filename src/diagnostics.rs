@@ -0,0 +1,244 @@
+//! Turning permission and access errors into something a user can act on
+
+use crate::{ErrSources, Error, ErrorId};
+use rusb::{Context, Device, UsbContext};
+use std::path::{Path, PathBuf};
+
+/// The device node libusb opens for a USB device, on systems which have one
+fn usb_node_path(bus: u8, address: u8) -> Option<PathBuf> {
+	if cfg!(target_os = "linux") {
+		Some(PathBuf::from(format!("/dev/bus/usb/{bus:03}/{address:03}")))
+	} else {
+		None
+	}
+}
+
+/// Every process with `path` open, as (PID, name)
+///
+/// Only works on Linux, and only finds processes whose open files the
+/// current user is allowed to see.
+fn find_holders(path: &Path) -> Vec<(u32, String)> {
+	let Ok(processes) = std::fs::read_dir("/proc") else {
+		return Vec::new();
+	};
+	let mut holders = Vec::new();
+	for process in processes.flatten() {
+		let Some(pid) =
+			process.file_name().to_str().and_then(|x| x.parse().ok())
+		else {
+			continue;
+		};
+		let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+			continue;
+		};
+		if fds
+			.flatten()
+			.any(|x| std::fs::read_link(x.path()).is_ok_and(|x| x == path))
+		{
+			let name = std::fs::read_to_string(process.path().join("comm"))
+				.map(|x| x.trim().to_string())
+				.unwrap_or_else(|_| "an unknown process".to_string());
+			holders.push((pid, name));
+		}
+	}
+	holders
+}
+
+/// Describes the node a device was opened through, for error messages
+fn describe(path: Option<&Path>) -> String {
+	match path {
+		Some(path) => format!("the X52 at {}", path.display()),
+		None => "the X52".to_string(),
+	}
+}
+
+/// How to grant access to an X52 through udev
+fn udev_hint(subsystem: &str, vendor: u16, product: u16) -> String {
+	format!(
+		"To allow access, save the udev rule\n    SUBSYSTEM==\"{subsystem}\", \
+		 ATTRS{{idVendor}}==\"{vendor:04x}\", \
+		 ATTRS{{idProduct}}==\"{product:04x}\", TAG+=\"uaccess\"\nas \
+		 /etc/udev/rules.d/70-rx52.rules, run `sudo udevadm control --reload \
+		 && sudo udevadm trigger` and replug the X52"
+	)
+}
+
+/// How to get the X52 back from whichever processes hold `path`
+fn busy_hint(path: Option<&Path>) -> String {
+	let holders = path.map(find_holders).unwrap_or_default();
+	if holders.is_empty() {
+		return "Close any other program using the X52, such as gx52 or \
+		        another program built on rx52"
+			.to_string();
+	}
+	let holders = holders
+		.iter()
+		.map(|(pid, name)| format!("{name} (PID {pid})"))
+		.collect::<Vec<_>>()
+		.join(", ");
+	format!("It is held by {holders}, which has to be closed first")
+}
+
+/// Adds diagnostics to errors from libusb on a device with the given node
+/// path and IDs
+fn diagnose(
+	err: rusb::Error,
+	path: Option<PathBuf>,
+	vendor: u16,
+	product: u16,
+) -> Error {
+	let mut error = match err {
+		rusb::Error::Access => {
+			let mut error = Error::new(
+				ErrorId::PermissionDenied,
+				format!(
+					"Permission denied opening {}",
+					describe(path.as_deref())
+				),
+			);
+			if cfg!(target_os = "linux") {
+				error.details_mut().hint =
+					Some(udev_hint("usb", vendor, product));
+			} else if cfg!(target_os = "windows") {
+				error.details_mut().hint = Some(
+					"The X52 needs the WinUSB driver, which can be installed \
+					 with Zadig"
+						.to_string(),
+				);
+			}
+			error
+		}
+		rusb::Error::Busy => {
+			let mut error = Error::new(
+				ErrorId::DeviceBusy,
+				format!(
+					"Another program is using {}",
+					describe(path.as_deref())
+				),
+			);
+			error.details_mut().hint = Some(busy_hint(path.as_deref()));
+			error
+		}
+		_ => return err.into(),
+	};
+	error.source = Some(ErrSources::Rusb(err));
+	error.details_mut().device_path = path;
+	error
+}
+
+/// Adds diagnostics to an error from libusb about the given device
+pub(crate) fn diagnose_usb(
+	err: rusb::Error,
+	device: &Device<Context>,
+) -> Error {
+	let (vendor, product) = match device.device_descriptor() {
+		Ok(x) => (x.vendor_id(), x.product_id()),
+		Err(_) => (0, 0),
+	};
+	diagnose(
+		err,
+		usb_node_path(device.bus_number(), device.address()),
+		vendor,
+		product,
+	)
+}
+
+/// Adds diagnostics to an error from detaching the kernel driver of an
+/// interface
+pub(crate) fn diagnose_kernel_driver<T: UsbContext>(
+	err: rusb::Error,
+	device: &Device<T>,
+	iface: u8,
+) -> Error {
+	let path = usb_node_path(device.bus_number(), device.address());
+	let mut error = Error::new(
+		ErrorId::KernelDriverActive,
+		format!(
+			"A kernel driver is bound to interface {iface} of {}, and could \
+			 not be detached: {err}",
+			describe(path.as_deref())
+		),
+	);
+	let hint = match err {
+		rusb::Error::NotSupported => {
+			"This platform can't detach kernel drivers, so input reports \
+			 can't be read through libusb"
+		}
+		_ if cfg!(target_os = "linux") => {
+			"Detaching the kernel driver needs write access to the device \
+			 node. Input reports can also be read through HidrawTransport, \
+			 which leaves the kernel driver alone"
+		}
+		_ => "Input reports can't be read while the kernel driver is bound",
+	};
+	error.details_mut().hint = Some(hint.to_string());
+	error.source = Some(ErrSources::Rusb(err));
+	error.details_mut().device_path = path;
+	error
+}
+
+/// Adds diagnostics to an error from opening a hidraw node
+#[cfg(target_os = "linux")]
+pub(crate) fn diagnose_hidraw(
+	err: std::io::Error,
+	path: &Path,
+	vendor: u16,
+	product: u16,
+) -> Error {
+	let (id, msg, hint) = match err.kind() {
+		std::io::ErrorKind::PermissionDenied => (
+			ErrorId::PermissionDenied,
+			format!("Permission denied opening {}", describe(Some(path))),
+			udev_hint("hidraw", vendor, product),
+		),
+		_ if err.raw_os_error() == Some(libc::EBUSY) => (
+			ErrorId::DeviceBusy,
+			format!("Another program is using {}", describe(Some(path))),
+			busy_hint(Some(path)),
+		),
+		_ => return err.into(),
+	};
+	let mut error = Error::from(err);
+	error.maybe_id = Some(id);
+	error.msg = msg;
+	error.details_mut().hint = Some(hint);
+	error.details_mut().device_path = Some(path.to_path_buf());
+	error
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn access_is_permission_denied() {
+		let err = diagnose(
+			rusb::Error::Access,
+			Some(PathBuf::from("/dev/bus/usb/001/004")),
+			0x06A3,
+			0x0762,
+		);
+		assert_eq!(err.id(), Some(ErrorId::PermissionDenied));
+		assert_eq!(err.rusb_error(), Some(rusb::Error::Access));
+		assert_eq!(err.device_path(), Some(Path::new("/dev/bus/usb/001/004")));
+		if cfg!(target_os = "linux") {
+			assert!(err.hint().unwrap().contains("ATTRS{idProduct}==\"0762\""));
+		}
+	}
+
+	#[test]
+	fn other_errors_are_untouched() {
+		let err = diagnose(rusb::Error::NoDevice, None, 0x06A3, 0x0762);
+		assert_eq!(err.id(), Some(ErrorId::DeviceDisconnected));
+		assert_eq!(err.hint(), None);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn holders_include_this_process() {
+		let path = std::env::current_exe().unwrap();
+		let _file = std::fs::File::open(&path).unwrap();
+		let holders = find_holders(&path);
+		assert!(holders.iter().any(|x| x.0 == std::process::id()));
+	}
+}
@@ -0,0 +1,414 @@
+//! Talking to X52s through the Linux hidraw interface
+
+use crate::diagnostics::diagnose_hidraw;
+use crate::transport::REQUEST_TIMEOUT;
+use crate::{
+	find_descriptor_by_ids, Error, ErrorId, UsbTransport, X52Descriptor,
+	X52DeviceId, X52DeviceType, X52Driver, X52Transport,
+};
+use rusb::{Context, UsbContext};
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Where the kernel lists every hidraw node
+const HIDRAW_CLASS_DIR: &str = "/sys/class/hidraw";
+/// The bus type of USB devices in `HID_ID`
+const HID_BUS_USB: u16 = 0x0003;
+
+/// Turns an error from a hidraw node into an [`Error`]
+fn hidraw_error(err: std::io::Error) -> Error {
+	let disconnected = err.raw_os_error() == Some(libc::ENODEV);
+	let mut err = Error::from(err);
+	if disconnected {
+		err.maybe_id = Some(ErrorId::DeviceDisconnected);
+	}
+	err
+}
+
+/// Parses the `HID_ID` line of a hidraw uevent file into (bus, vendor,
+/// product)
+fn parse_hid_id(uevent: &str) -> Option<(u16, u16, u16)> {
+	let id = uevent.lines().find_map(|x| x.strip_prefix("HID_ID="))?;
+	let mut parts = id.split(':').map(|x| u32::from_str_radix(x, 16).ok());
+	let bus = parts.next()??;
+	let vendor = parts.next()??;
+	let product = parts.next()??;
+	Some((bus as u16, vendor as u16, product as u16))
+}
+
+/// Finds the sysfs directory of the USB device a hidraw node belongs to
+///
+/// `device` is the resolved `device` link of the hidraw node, which looks
+/// like `.../usb1/1-2/1-2:1.0/0003:06A3:0762.0001`.
+fn usb_device_dir(device: &Path) -> Option<&Path> {
+	device.ancestors().find(|x| {
+		x.file_name()
+			.and_then(|x| x.to_str())
+			.is_some_and(|x| x.parse::<X52DeviceId>().is_ok())
+	})
+}
+
+/// Reads a sysfs attribute as a trimmed string
+fn read_attribute(dir: &Path, name: &str) -> Option<String> {
+	Some(fs::read_to_string(dir.join(name)).ok()?.trim().to_string())
+}
+
+/// What sysfs says about a hidraw node of a USB device
+struct HidrawNode {
+	vendor: u16,
+	product: u16,
+	/// The sysfs directory of the USB device, if it could be found
+	usb_dir: Option<PathBuf>,
+}
+
+impl HidrawNode {
+	/// Reads the sysfs entry of the hidraw node called `name`
+	///
+	/// Returns [`None`] if there is no such node or it isn't on USB.
+	fn read(name: &OsStr) -> Option<Self> {
+		let node = Path::new(HIDRAW_CLASS_DIR).join(name);
+		let uevent = fs::read_to_string(node.join("device/uevent")).ok()?;
+		let (HID_BUS_USB, vendor, product) = parse_hid_id(&uevent)? else {
+			return None;
+		};
+		let device = fs::canonicalize(node.join("device")).ok();
+		Some(Self {
+			vendor,
+			product,
+			usb_dir: device
+				.as_deref()
+				.and_then(usb_device_dir)
+				.map(Path::to_path_buf),
+		})
+	}
+
+	/// The (bus, device) of the USB device
+	fn bus_device(&self) -> Option<(u8, u8)> {
+		let dir = self.usb_dir.as_deref()?;
+		Some((
+			read_attribute(dir, "busnum")?.parse().ok()?,
+			read_attribute(dir, "devnum")?.parse().ok()?,
+		))
+	}
+}
+
+/// An X52 found through hidraw, which can be opened with
+/// [`X52HidrawEntry::open`]
+///
+/// Only available on Linux.
+#[derive(Debug, Clone)]
+pub struct X52HidrawEntry {
+	path: PathBuf,
+	descriptor: &'static X52Descriptor,
+	usb_dir: Option<PathBuf>,
+}
+
+impl X52HidrawEntry {
+	/// The path of the hidraw node, like `/dev/hidraw3`
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The descriptor matching this X52
+	pub fn descriptor(&self) -> &'static X52Descriptor {
+		self.descriptor
+	}
+
+	/// The type of this X52
+	pub fn x52_type(&self) -> &'static X52DeviceType {
+		self.descriptor.x52_type()
+	}
+
+	/// The stable ID of this X52, if its USB device could be found in sysfs
+	pub fn id(&self) -> Option<X52DeviceId> {
+		let dir = self.usb_dir.as_ref()?;
+		let id = dir.file_name()?.to_str()?.parse::<X52DeviceId>().ok()?;
+		Some(X52DeviceId::new(
+			id.bus(),
+			id.port_path().to_vec(),
+			read_attribute(dir, "serial"),
+		))
+	}
+
+	/// Opens the hidraw node and creates a driver for it
+	///
+	/// Fails with [`ErrorId::PermissionDenied`] if the current user can't
+	/// open the node, see [`Error::hint`] for how to fix it. Commands still
+	/// need access to the USB device node, see [`HidrawTransport`].
+	pub fn open(&self) -> Result<X52Driver<HidrawTransport>, Error> {
+		Ok(X52Driver::new(
+			HidrawTransport::open(&self.path)?,
+			self.x52_type(),
+		))
+	}
+}
+
+impl Display for X52HidrawEntry {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		write!(
+			fmt,
+			"{}: {}",
+			self.path.display(),
+			self.descriptor.description()
+		)
+	}
+}
+
+/// Lists every X52 which has a hidraw node
+///
+/// Only available on Linux. Devices are matched against the same vendor and
+/// product IDs as [`get_devices`](crate::get_devices).
+///
+/// # Examples
+///
+/// ```no_run
+/// for device in rx52::get_hidraw_devices().unwrap() {
+///     println!("{device}");
+/// }
+/// ```
+pub fn get_hidraw_devices() -> Result<Vec<X52HidrawEntry>, Error> {
+	let mut devices = Vec::new();
+	for node in fs::read_dir(HIDRAW_CLASS_DIR)? {
+		let name = node?.file_name();
+		let Some(node) = HidrawNode::read(&name) else {
+			continue;
+		};
+		let Some(descriptor) =
+			find_descriptor_by_ids(node.vendor, node.product)
+		else {
+			continue;
+		};
+		devices.push(X52HidrawEntry {
+			path: Path::new("/dev").join(name),
+			descriptor,
+			usb_dir: node.usb_dir,
+		});
+	}
+	devices.sort_by(|x, y| x.path.cmp(&y.path));
+	Ok(devices)
+}
+
+/// A transport which reads input reports from a Linux hidraw node
+///
+/// Only available on Linux. Access to `/dev/hidraw*` is easy to grant to a
+/// user with udev, and unlike [`UsbTransport`] reading input reports doesn't
+/// take the joystick away from the kernel.
+///
+/// <div class="warning">This is not a replacement for libusb access. The X52
+/// takes its commands as USB vendor requests, which hidraw has no way of
+/// sending, so commands still go through libusb to the USB device node under
+/// <code>/dev/bus/usb</code>. That node is opened the first time a command
+/// is sent, and the user needs write access to it, usually through the same
+/// udev rule as for <code>UsbTransport</code>. No interface is claimed, so
+/// the kernel driver stays bound.</div>
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let entry = &rx52::get_hidraw_devices().unwrap()[0];
+/// let driver = entry.open().unwrap();
+///
+/// let mut report = [0; 64];
+/// let len = driver
+///     .read_report(&mut report, Duration::from_secs(1))
+///     .unwrap();
+/// println!("{:02X?}", &report[..len]);
+/// ```
+pub struct HidrawTransport {
+	file: File,
+	path: PathBuf,
+	/// The (bus, device) of the USB device, as last read from sysfs
+	bus_device: Mutex<Option<(u8, u8)>>,
+	/// The USB device commands are sent to, once one has been sent
+	control: Mutex<Option<UsbTransport>>,
+	/// How long to wait for commands
+	timeout: Mutex<Duration>,
+}
+
+impl HidrawTransport {
+	/// Opens a hidraw node
+	///
+	/// This does not check that the node actually belongs to an X52. Fails
+	/// with [`ErrorId::PermissionDenied`] or [`ErrorId::DeviceBusy`] if the
+	/// node can't be opened, see [`Error::hint`] for how to fix it.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		let path = path.as_ref().to_path_buf();
+		let node = path.file_name().and_then(HidrawNode::read);
+		let file = File::open(&path).map_err(|x| {
+			if x.raw_os_error() == Some(libc::ENODEV) {
+				return hidraw_error(x);
+			}
+			let (vendor, product) =
+				node.as_ref().map_or((0, 0), |x| (x.vendor, x.product));
+			diagnose_hidraw(x, &path, vendor, product)
+		})?;
+		Ok(Self {
+			file,
+			path,
+			bus_device: Mutex::new(
+				node.as_ref().and_then(HidrawNode::bus_device),
+			),
+			control: Mutex::new(None),
+			timeout: Mutex::new(REQUEST_TIMEOUT),
+		})
+	}
+
+	/// The path of the hidraw node
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Waits until there is a report to read, returning false on timeout
+	fn wait_readable(&self, timeout: Duration) -> Result<bool, Error> {
+		let mut fd = libc::pollfd {
+			fd: self.file.as_raw_fd(),
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		let millis = timeout.as_millis().min(libc::c_int::MAX as u128);
+		// SAFETY: fd points to exactly one valid pollfd, which outlives the
+		// call
+		let ready = unsafe { libc::poll(&mut fd, 1, millis as libc::c_int) };
+		if ready < 0 {
+			return Err(hidraw_error(std::io::Error::last_os_error()));
+		}
+		if fd.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0 {
+			return Err(hidraw_error(std::io::Error::from_raw_os_error(
+				libc::ENODEV,
+			)));
+		}
+		Ok(ready > 0)
+	}
+
+	/// Opens the USB device node of the X52 to send commands through
+	///
+	/// The USB device is looked up in sysfs again each time, as its address
+	/// changes when it is replugged.
+	fn open_control(&self) -> Result<UsbTransport, Error> {
+		let bus_device = self
+			.path
+			.file_name()
+			.and_then(HidrawNode::read)
+			.and_then(|x| x.bus_device());
+		*self.bus_device.lock().unwrap() = bus_device;
+		let Some((bus, address)) = bus_device else {
+			return Err(Error::new(
+				ErrorId::NotSupported,
+				format!(
+					"The USB device of {} could not be found, which the X52 \
+					 needs for commands",
+					self.path.display()
+				),
+			));
+		};
+		let device = Context::new()?
+			.devices()?
+			.iter()
+			.find(|x| x.bus_number() == bus && x.address() == address)
+			.ok_or(Error::from(rusb::Error::NoDevice))?;
+		let control = UsbTransport::open(device)?;
+		control.set_timeout(*self.timeout.lock().unwrap());
+		Ok(control)
+	}
+}
+
+impl std::fmt::Debug for HidrawTransport {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		fmt.debug_struct("HidrawTransport")
+			.field("path", &self.path)
+			.field("bus_device", &*self.bus_device.lock().unwrap())
+			.finish_non_exhaustive()
+	}
+}
+
+impl X52Transport for HidrawTransport {
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error> {
+		let mut control = self.control.lock().unwrap();
+		if control.is_none() {
+			*control = Some(self.open_control()?);
+		}
+		let result = control.as_ref().unwrap().vendor_command(index, value);
+		// Open the device again next time, as it may have been replugged
+		if result
+			.as_ref()
+			.is_err_and(|x| x.id() == Some(ErrorId::DeviceDisconnected))
+		{
+			*control = None;
+		}
+		result
+	}
+
+	fn read_report(
+		&self,
+		buf: &mut [u8],
+		timeout: Duration,
+	) -> Result<usize, Error> {
+		if !self.wait_readable(timeout)? {
+			return Err(rusb::Error::Timeout.into());
+		}
+		(&self.file).read(buf).map_err(hidraw_error)
+	}
+
+	fn bus_device(&self) -> Option<(u8, u8)> {
+		*self.bus_device.lock().unwrap()
+	}
+
+	fn set_timeout(&self, timeout: Duration) {
+		*self.timeout.lock().unwrap() = timeout;
+		if let Some(control) = &*self.control.lock().unwrap() {
+			control.set_timeout(timeout);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hid_id_is_parsed() {
+		let uevent = "DRIVER=hid-generic\nHID_ID=0003:000006A3:00000762\n\
+		              HID_NAME=Saitek Saitek X52 Pro Flight Control System\n";
+		assert_eq!(parse_hid_id(uevent), Some((0x0003, 0x06A3, 0x0762)));
+		assert_eq!(parse_hid_id("HID_NAME=X52\n"), None);
+		assert_eq!(parse_hid_id("HID_ID=0003:zz:0762\n"), None);
+	}
+
+	#[test]
+	fn usb_device_dir_is_found() {
+		let device = Path::new(
+			"/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.4/1-2.4:1.0/\
+			 0003:06A3:0762.0001",
+		);
+		assert_eq!(
+			usb_device_dir(device),
+			Some(Path::new(
+				"/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2.4"
+			))
+		);
+	}
+
+	#[test]
+	fn commands_need_the_usb_device() {
+		let transport = HidrawTransport {
+			file: File::open("/dev/null").unwrap(),
+			path: PathBuf::from("/dev/null"),
+			// Left over from before a replug
+			bus_device: Mutex::new(Some((1, 2))),
+			control: Mutex::new(None),
+			timeout: Mutex::new(REQUEST_TIMEOUT),
+		};
+		let err = transport.vendor_command(0xB1, 3).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::NotSupported));
+		assert!(transport.control.lock().unwrap().is_none());
+		assert_eq!(transport.bus_device(), None);
+	}
+}
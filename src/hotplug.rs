@@ -0,0 +1,430 @@
+//! Watching for X52s being plugged in and unplugged
+
+use crate::device::{get_devices_in, X52DeviceEntry};
+use crate::{Error, X52Descriptor, X52DeviceId};
+use rusb::{Context, Device, Hotplug, HotplugBuilder, UsbContext};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the polling fallback looks for changes by default
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// How long the watcher thread waits for libusb events before checking if it
+/// should stop
+const EVENT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Something that happened to an X52
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum X52HotplugEvent {
+	/// An X52 was plugged in
+	Connected {
+		/// The descriptor the X52 matched
+		descriptor: &'static X52Descriptor,
+		/// The stable ID of the X52
+		id: X52DeviceId,
+		/// The (bus, device) the X52 can currently be found at
+		bus_device: (u8, u8),
+	},
+	/// An X52 was unplugged
+	Disconnected {
+		/// The descriptor the X52 matched
+		descriptor: &'static X52Descriptor,
+		/// The stable ID the X52 had when it was connected
+		id: X52DeviceId,
+		/// The (bus, device) the X52 could be found at
+		bus_device: (u8, u8),
+	},
+}
+
+impl X52HotplugEvent {
+	/// The stable ID of the X52 the event is about
+	pub fn id(&self) -> &X52DeviceId {
+		match self {
+			Self::Connected { id, .. } => id,
+			Self::Disconnected { id, .. } => id,
+		}
+	}
+
+	/// The descriptor of the X52 the event is about
+	pub fn descriptor(&self) -> &'static X52Descriptor {
+		match self {
+			Self::Connected { descriptor, .. } => descriptor,
+			Self::Disconnected { descriptor, .. } => descriptor,
+		}
+	}
+}
+
+/// The X52s the watcher currently knows about, keyed by (bus, device)
+struct KnownDevices {
+	devices: HashMap<(u8, u8), (&'static X52Descriptor, X52DeviceId)>,
+	sender: Sender<X52HotplugEvent>,
+}
+
+impl KnownDevices {
+	fn connected(&mut self, entry: X52DeviceEntry) {
+		let bus_device = (entry.bus(), entry.address());
+		self.insert(bus_device, entry.descriptor(), || entry.id());
+	}
+
+	/// Reports the X52 at `bus_device` as connected, unless it already is
+	///
+	/// `id` is only called for new X52s, as reading the serial number means
+	/// talking to the device.
+	fn insert<F: FnOnce() -> X52DeviceId>(
+		&mut self,
+		bus_device: (u8, u8),
+		descriptor: &'static X52Descriptor,
+		id: F,
+	) {
+		if self.devices.contains_key(&bus_device) {
+			return;
+		}
+		let id = id();
+		self.devices.insert(bus_device, (descriptor, id.clone()));
+		// The receiving end going away just means nobody is listening
+		let _ = self.sender.send(X52HotplugEvent::Connected {
+			descriptor,
+			id,
+			bus_device,
+		});
+	}
+
+	fn disconnected(&mut self, bus_device: (u8, u8)) {
+		if let Some((descriptor, id)) = self.devices.remove(&bus_device) {
+			let _ = self.sender.send(X52HotplugEvent::Disconnected {
+				descriptor,
+				id,
+				bus_device,
+			});
+		}
+	}
+
+	/// Reports every known X52 which isn't in `present` as disconnected
+	fn retain(&mut self, present: &[(u8, u8)]) {
+		let gone = self
+			.devices
+			.keys()
+			.filter(|x| !present.contains(x))
+			.copied()
+			.collect::<Vec<_>>();
+		for bus_device in gone {
+			self.disconnected(bus_device);
+		}
+	}
+}
+
+/// Collects libusb hotplug callbacks to be handled outside of libusb
+///
+/// libusb does not allow synchronous requests, such as reading the serial
+/// number, from inside the callback, so devices are only queued up here.
+struct HotplugQueue {
+	queue: QueuedDevices,
+}
+
+/// Devices queued by [`HotplugQueue`], and whether they arrived or left
+type QueuedDevices = Arc<Mutex<Vec<(bool, Device<Context>)>>>;
+
+impl Hotplug<Context> for HotplugQueue {
+	fn device_arrived(&mut self, device: Device<Context>) {
+		self.queue.lock().unwrap().push((true, device));
+	}
+
+	fn device_left(&mut self, device: Device<Context>) {
+		self.queue.lock().unwrap().push((false, device));
+	}
+}
+
+/// Watches the devices attached to `ctx`, with libusb hotplug callbacks if
+/// libusb supports them and by polling otherwise
+fn watch_in(ctx: Context, known: KnownDevices, stop: Arc<AtomicBool>) {
+	if rusb::has_hotplug() {
+		watch_hotplug(ctx, known, stop)
+	} else {
+		watch_polling(ctx, known, stop, DEFAULT_POLL_INTERVAL)
+	}
+}
+
+/// Watches the libusb hotplug callbacks for X52s
+///
+/// Falls back to polling if the callback can't be registered.
+fn watch_hotplug(ctx: Context, mut known: KnownDevices, stop: Arc<AtomicBool>) {
+	let queue: QueuedDevices = Arc::new(Mutex::new(Vec::new()));
+	let registration = HotplugBuilder::new().enumerate(true).register(
+		&ctx,
+		Box::new(HotplugQueue {
+			queue: queue.clone(),
+		}),
+	);
+	let Ok(_registration) = registration else {
+		return watch_polling(ctx, known, stop, DEFAULT_POLL_INTERVAL);
+	};
+	while !stop.load(Ordering::Relaxed) {
+		if ctx.handle_events(Some(EVENT_TIMEOUT)).is_err() {
+			break;
+		}
+		let events = std::mem::take(&mut *queue.lock().unwrap());
+		for (arrived, device) in events {
+			if arrived {
+				if let Some(entry) = X52DeviceEntry::from_device(device) {
+					known.connected(entry);
+				}
+			} else {
+				known.disconnected((device.bus_number(), device.address()));
+			}
+		}
+	}
+}
+
+/// Watches for X52s by listing the attached devices every `interval`
+fn watch_polling(
+	ctx: Context,
+	mut known: KnownDevices,
+	stop: Arc<AtomicBool>,
+	interval: Duration,
+) {
+	while !stop.load(Ordering::Relaxed) {
+		if let Ok(entries) = get_devices_in(&ctx) {
+			let present = entries
+				.iter()
+				.map(|x| (x.bus(), x.address()))
+				.collect::<Vec<_>>();
+			known.retain(&present);
+			for entry in entries {
+				known.connected(entry);
+			}
+		}
+		// Sleep in small steps so dropping the watcher doesn't hang
+		let mut slept = Duration::ZERO;
+		while slept < interval && !stop.load(Ordering::Relaxed) {
+			let step = EVENT_TIMEOUT.min(interval - slept);
+			thread::sleep(step);
+			slept += step;
+		}
+	}
+}
+
+/// Watches for X52s being plugged in and unplugged
+///
+/// Events are delivered from a background thread, which stops when the
+/// watcher is dropped. Every X52 already attached when the watcher is created
+/// is reported as [`X52HotplugEvent::Connected`] first.
+///
+/// libusb hotplug callbacks are used where the platform supports them, and
+/// the attached devices are polled otherwise.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::{X52HotplugEvent, X52HotplugWatcher};
+///
+/// let watcher = X52HotplugWatcher::new().unwrap();
+/// for event in watcher.iter() {
+///     match event {
+///         X52HotplugEvent::Connected { id, .. } => println!("{id} connected"),
+///         X52HotplugEvent::Disconnected { id, .. } => println!("{id} gone"),
+///     }
+/// }
+/// ```
+pub struct X52HotplugWatcher {
+	receiver: Receiver<X52HotplugEvent>,
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl X52HotplugWatcher {
+	/// Starts watching, using libusb hotplug callbacks if possible
+	pub fn new() -> Result<Self, Error> {
+		Ok(Self::new_in(Context::new()?))
+	}
+
+	/// Starts watching by polling the attached devices every `interval`
+	///
+	/// Only useful where libusb hotplug callbacks are known not to work,
+	/// [`X52HotplugWatcher::new`] already falls back to this by itself.
+	pub fn polling(interval: Duration) -> Result<Self, Error> {
+		let ctx = Context::new()?;
+		Ok(Self::spawn(move |known, stop| {
+			watch_polling(ctx, known, stop, interval)
+		}))
+	}
+
+	/// Starts watching the devices attached to the given context
+	pub(crate) fn new_in(ctx: Context) -> Self {
+		Self::spawn(move |known, stop| watch_in(ctx, known, stop))
+	}
+
+	/// Starts watching in a context created on the watcher thread
+	///
+	/// If libusb fails to create it, the watcher stops straight away.
+	pub(crate) fn new_lazy() -> Self {
+		Self::spawn(|known, stop| {
+			if let Ok(ctx) = Context::new() {
+				watch_in(ctx, known, stop)
+			}
+		})
+	}
+
+	/// A watcher whose thread has already stopped
+	#[cfg(all(test, feature = "tokio"))]
+	pub(crate) fn stopped() -> Self {
+		let watcher = Self::spawn(|_, _| {});
+		while !watcher.thread.as_ref().is_some_and(|x| x.is_finished()) {
+			thread::yield_now();
+		}
+		watcher
+	}
+
+	/// Runs `watch` on the watcher thread
+	fn spawn<F>(watch: F) -> Self
+	where
+		F: FnOnce(KnownDevices, Arc<AtomicBool>) + Send + 'static,
+	{
+		let (sender, receiver) = mpsc::channel();
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_stop = stop.clone();
+		let thread = thread::spawn(move || {
+			let known = KnownDevices {
+				devices: HashMap::new(),
+				sender,
+			};
+			watch(known, thread_stop)
+		});
+		Self {
+			receiver,
+			stop,
+			thread: Some(thread),
+		}
+	}
+
+	/// Waits for the next event
+	///
+	/// Returns [`None`] if the watcher thread stopped, which only happens if
+	/// libusb fails.
+	pub fn recv(&self) -> Option<X52HotplugEvent> {
+		self.receiver.recv().ok()
+	}
+
+	/// Returns the next event if one has already happened
+	pub fn try_recv(&self) -> Option<X52HotplugEvent> {
+		self.receiver.try_recv().ok()
+	}
+
+	/// Waits up to `timeout` for the next event
+	///
+	/// Returns [`None`] both on a timeout and if the watcher thread stopped,
+	/// use [`X52HotplugWatcher::try_recv_timeout`] to tell them apart.
+	pub fn recv_timeout(&self, timeout: Duration) -> Option<X52HotplugEvent> {
+		self.receiver.recv_timeout(timeout).ok()
+	}
+
+	/// Waits up to `timeout` for the next event
+	///
+	/// Fails with [`RecvTimeoutError::Disconnected`] once the watcher thread
+	/// stopped and every event it sent has been received.
+	pub fn try_recv_timeout(
+		&self,
+		timeout: Duration,
+	) -> Result<X52HotplugEvent, RecvTimeoutError> {
+		self.receiver.recv_timeout(timeout)
+	}
+
+	/// An iterator which blocks waiting for events
+	pub fn iter(&self) -> impl Iterator<Item = X52HotplugEvent> + '_ {
+		self.receiver.iter()
+	}
+}
+
+impl Drop for X52HotplugWatcher {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::POSSIBLE_DESCRIPTORS;
+
+	fn known_devices() -> (KnownDevices, Receiver<X52HotplugEvent>) {
+		let (sender, receiver) = mpsc::channel();
+		let known = KnownDevices {
+			devices: HashMap::new(),
+			sender,
+		};
+		(known, receiver)
+	}
+
+	fn id(port: u8) -> X52DeviceId {
+		X52DeviceId::new(1, vec![port], None)
+	}
+
+	#[test]
+	fn devices_are_only_connected_once() {
+		let (mut known, receiver) = known_devices();
+		let descriptor = &POSSIBLE_DESCRIPTORS[0];
+		known.insert((1, 4), descriptor, || id(2));
+		known.insert((1, 4), descriptor, || panic!("ID read again"));
+		assert_eq!(
+			receiver.try_iter().collect::<Vec<_>>(),
+			vec![X52HotplugEvent::Connected {
+				descriptor,
+				id: id(2),
+				bus_device: (1, 4),
+			}]
+		);
+	}
+
+	#[test]
+	fn only_known_devices_are_disconnected() {
+		let (mut known, receiver) = known_devices();
+		let descriptor = &POSSIBLE_DESCRIPTORS[0];
+		known.disconnected((1, 4));
+		assert!(receiver.try_recv().is_err());
+		known.insert((1, 4), descriptor, || id(2));
+		known.disconnected((1, 4));
+		known.disconnected((1, 4));
+		let events = receiver.try_iter().collect::<Vec<_>>();
+		assert_eq!(events.len(), 2);
+		assert_eq!(
+			events[1],
+			X52HotplugEvent::Disconnected {
+				descriptor,
+				id: id(2),
+				bus_device: (1, 4),
+			}
+		);
+		// Replugged at the same address
+		known.insert((1, 4), descriptor, || id(2));
+		assert!(matches!(
+			receiver.try_recv(),
+			Ok(X52HotplugEvent::Connected { .. })
+		));
+	}
+
+	#[test]
+	fn missing_devices_are_gone() {
+		let (mut known, receiver) = known_devices();
+		let descriptor = &POSSIBLE_DESCRIPTORS[0];
+		known.insert((1, 4), descriptor, || id(2));
+		known.insert((1, 5), descriptor, || id(3));
+		receiver.try_iter().for_each(drop);
+		known.retain(&[(1, 5), (2, 7)]);
+		assert_eq!(
+			receiver.try_iter().collect::<Vec<_>>(),
+			vec![X52HotplugEvent::Disconnected {
+				descriptor,
+				id: id(2),
+				bus_device: (1, 4),
+			}]
+		);
+		known.retain(&[]);
+		assert_eq!(receiver.try_iter().count(), 1);
+		assert!(known.devices.is_empty());
+	}
+}
@@ -0,0 +1,353 @@
+//! Everything USB says about an X52, for bug reports
+
+use crate::{find_descriptor, Error, X52Descriptor, X52DeviceId};
+use rusb::{
+	Context, Device, DeviceHandle, Direction, Speed, TransferType, Version,
+};
+use std::fmt::{Display, Formatter};
+
+/// An endpoint of an [`X52InterfaceInfo`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X52EndpointInfo {
+	address: u8,
+	direction: Direction,
+	transfer_type: TransferType,
+	max_packet_size: u16,
+	interval: u8,
+}
+
+impl X52EndpointInfo {
+	/// The address of the endpoint, including the direction bit
+	pub fn address(&self) -> u8 {
+		self.address
+	}
+
+	/// Whether the endpoint is IN (to the host) or OUT
+	pub fn direction(&self) -> Direction {
+		self.direction
+	}
+
+	/// The kind of transfers the endpoint is used for
+	pub fn transfer_type(&self) -> TransferType {
+		self.transfer_type
+	}
+
+	/// The largest packet the endpoint sends or receives
+	pub fn max_packet_size(&self) -> u16 {
+		self.max_packet_size
+	}
+
+	/// How often the endpoint is polled, in frames
+	pub fn interval(&self) -> u8 {
+		self.interval
+	}
+}
+
+/// An interface of the active configuration of an X52, see
+/// [`X52DeviceInfo::interfaces`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X52InterfaceInfo {
+	number: u8,
+	alt_setting: u8,
+	class: u8,
+	sub_class: u8,
+	protocol: u8,
+	endpoints: Vec<X52EndpointInfo>,
+}
+
+impl X52InterfaceInfo {
+	/// The number of the interface
+	pub fn number(&self) -> u8 {
+		self.number
+	}
+
+	/// The alternate setting this describes
+	pub fn alt_setting(&self) -> u8 {
+		self.alt_setting
+	}
+
+	/// The USB class code of the interface, 3 for HID
+	pub fn class(&self) -> u8 {
+		self.class
+	}
+
+	/// The USB subclass code of the interface
+	pub fn sub_class(&self) -> u8 {
+		self.sub_class
+	}
+
+	/// The USB protocol code of the interface
+	pub fn protocol(&self) -> u8 {
+		self.protocol
+	}
+
+	/// The endpoints of the interface
+	pub fn endpoints(&self) -> &[X52EndpointInfo] {
+		&self.endpoints
+	}
+}
+
+/// What USB says about an X52, from [`X52Driver::device_info`]
+///
+/// Displaying it gives a summary to paste into bug reports.
+///
+/// [`X52Driver::device_info`]: crate::X52Driver::device_info
+///
+/// # Examples
+///
+/// ```no_run
+/// let driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// let info = driver.device_info().unwrap();
+/// println!("Firmware revision {}", info.device_version());
+/// println!("{info}");
+/// ```
+#[derive(Debug, Clone)]
+pub struct X52DeviceInfo {
+	descriptor: Option<&'static X52Descriptor>,
+	vendor: u16,
+	product: u16,
+	manufacturer: Option<String>,
+	product_name: Option<String>,
+	serial: Option<String>,
+	device_version: Version,
+	usb_version: Version,
+	speed: Speed,
+	bus: u8,
+	address: u8,
+	port_path: Vec<u8>,
+	interfaces: Vec<X52InterfaceInfo>,
+}
+
+impl X52DeviceInfo {
+	/// Reads everything about `device`
+	///
+	/// The strings are only read if `handle` is given, as that needs the
+	/// device to be opened.
+	pub(crate) fn read(
+		device: &Device<Context>,
+		handle: Option<&DeviceHandle<Context>>,
+	) -> Result<Self, Error> {
+		let descriptor = device.device_descriptor()?;
+		let read_string = |read: fn(
+			&DeviceHandle<Context>,
+			&rusb::DeviceDescriptor,
+		) -> rusb::Result<String>| {
+			handle.and_then(|x| read(x, &descriptor).ok())
+		};
+		let mut interfaces = Vec::new();
+		for interface in device.active_config_descriptor()?.interfaces() {
+			for setting in interface.descriptors() {
+				interfaces.push(X52InterfaceInfo {
+					number: setting.interface_number(),
+					alt_setting: setting.setting_number(),
+					class: setting.class_code(),
+					sub_class: setting.sub_class_code(),
+					protocol: setting.protocol_code(),
+					endpoints: setting
+						.endpoint_descriptors()
+						.map(|x| X52EndpointInfo {
+							address: x.address(),
+							direction: x.direction(),
+							transfer_type: x.transfer_type(),
+							max_packet_size: x.max_packet_size(),
+							interval: x.interval(),
+						})
+						.collect(),
+				});
+			}
+		}
+		Ok(Self {
+			descriptor: find_descriptor(&descriptor),
+			vendor: descriptor.vendor_id(),
+			product: descriptor.product_id(),
+			manufacturer: read_string(
+				DeviceHandle::read_manufacturer_string_ascii,
+			),
+			product_name: read_string(DeviceHandle::read_product_string_ascii),
+			serial: read_string(DeviceHandle::read_serial_number_string_ascii),
+			device_version: descriptor.device_version(),
+			usb_version: descriptor.usb_version(),
+			speed: device.speed(),
+			bus: device.bus_number(),
+			address: device.address(),
+			// Devices plugged straight into the root hub have no ports
+			port_path: device.port_numbers().unwrap_or_default(),
+			interfaces,
+		})
+	}
+
+	/// The descriptor the X52 matched, if it still matches one
+	pub fn descriptor(&self) -> Option<&'static X52Descriptor> {
+		self.descriptor
+	}
+
+	/// The USB vendor ID
+	pub fn vendor(&self) -> u16 {
+		self.vendor
+	}
+
+	/// The USB product ID
+	pub fn product(&self) -> u16 {
+		self.product
+	}
+
+	/// The manufacturer string, if the X52 has one
+	pub fn manufacturer(&self) -> Option<&str> {
+		self.manufacturer.as_deref()
+	}
+
+	/// The product string, if the X52 has one
+	pub fn product_name(&self) -> Option<&str> {
+		self.product_name.as_deref()
+	}
+
+	/// The serial number, if the X52 has one
+	pub fn serial(&self) -> Option<&str> {
+		self.serial.as_deref()
+	}
+
+	/// The release number of the X52 (bcdDevice), which is its firmware
+	/// revision
+	pub fn device_version(&self) -> Version {
+		self.device_version
+	}
+
+	/// The version of USB the X52 supports (bcdUSB)
+	pub fn usb_version(&self) -> Version {
+		self.usb_version
+	}
+
+	/// The speed the X52 is running at
+	pub fn speed(&self) -> Speed {
+		self.speed
+	}
+
+	/// The number of the bus the X52 is on
+	pub fn bus(&self) -> u8 {
+		self.bus
+	}
+
+	/// The address of the X52 on its bus
+	pub fn address(&self) -> u8 {
+		self.address
+	}
+
+	/// The ports leading from the root hub to the X52, outermost first
+	pub fn port_path(&self) -> &[u8] {
+		&self.port_path
+	}
+
+	/// The stable ID of the X52, see [`X52DeviceId`]
+	pub fn id(&self) -> X52DeviceId {
+		X52DeviceId::new(self.bus, self.port_path.clone(), self.serial.clone())
+	}
+
+	/// Every interface, and every alternate setting of each, of the active
+	/// configuration
+	pub fn interfaces(&self) -> &[X52InterfaceInfo] {
+		&self.interfaces
+	}
+}
+
+impl Display for X52DeviceInfo {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		let unknown = |x: &Option<String>| {
+			x.clone().unwrap_or_else(|| "(unknown)".to_string())
+		};
+		writeln!(
+			fmt,
+			"{:04x}:{:04x} {}",
+			self.vendor,
+			self.product,
+			self.descriptor
+				.map_or("not a known X52", |x| x.description())
+		)?;
+		writeln!(fmt, "Manufacturer: {}", unknown(&self.manufacturer))?;
+		writeln!(fmt, "Product: {}", unknown(&self.product_name))?;
+		writeln!(fmt, "Serial: {}", unknown(&self.serial))?;
+		writeln!(fmt, "Firmware (bcdDevice): {}", self.device_version)?;
+		writeln!(fmt, "USB version: {}", self.usb_version)?;
+		writeln!(fmt, "Speed: {:?}", self.speed)?;
+		write!(
+			fmt,
+			"Bus {:03} Device {:03}, ID {}",
+			self.bus,
+			self.address,
+			self.id()
+		)?;
+		for interface in &self.interfaces {
+			write!(
+				fmt,
+				"\nInterface {} alt {}: class {:02x}:{:02x}:{:02x}",
+				interface.number,
+				interface.alt_setting,
+				interface.class,
+				interface.sub_class,
+				interface.protocol
+			)?;
+			for endpoint in &interface.endpoints {
+				write!(
+					fmt,
+					"\n  Endpoint {:#04x} {:?} {:?}, {} bytes every {}",
+					endpoint.address,
+					endpoint.direction,
+					endpoint.transfer_type,
+					endpoint.max_packet_size,
+					endpoint.interval
+				)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::find_descriptor_by_ids;
+
+	#[test]
+	fn info_is_displayed() {
+		let info = X52DeviceInfo {
+			descriptor: find_descriptor_by_ids(0x06A3, 0x0762),
+			vendor: 0x06A3,
+			product: 0x0762,
+			manufacturer: Some("Saitek".to_string()),
+			product_name: None,
+			serial: None,
+			device_version: Version(1, 2, 3),
+			usb_version: Version(2, 0, 0),
+			speed: Speed::Full,
+			bus: 1,
+			address: 4,
+			port_path: vec![2, 4],
+			interfaces: vec![X52InterfaceInfo {
+				number: 0,
+				alt_setting: 0,
+				class: 3,
+				sub_class: 0,
+				protocol: 0,
+				endpoints: vec![X52EndpointInfo {
+					address: 0x81,
+					direction: Direction::In,
+					transfer_type: TransferType::Interrupt,
+					max_packet_size: 16,
+					interval: 10,
+				}],
+			}],
+		};
+		assert_eq!(
+			info.to_string(),
+			"06a3:0762 Saitek X52 Pro Flight Control System\n\
+			 Manufacturer: Saitek\n\
+			 Product: (unknown)\n\
+			 Serial: (unknown)\n\
+			 Firmware (bcdDevice): 1.2.3\n\
+			 USB version: 2.0.0\n\
+			 Speed: Full\n\
+			 Bus 001 Device 004, ID 1-2.4\n\
+			 Interface 0 alt 0: class 03:00:00\n  \
+			 Endpoint 0x81 In Interrupt, 16 bytes every 10"
+		);
+	}
+}
@@ -14,120 +14,68 @@
 )]
 #![warn(missing_docs)]
 
-use rusb::{
-	request_type, Context, Device, DeviceDescriptor, DeviceHandle, Direction,
-	Recipient, RequestType, UsbContext,
-};
+use rusb::{Context, Device, DeviceDescriptor, UsbContext};
 use std::error::Error as ErrorTrait;
-use std::fmt::{Debug, Display, Formatter};
+use std::fmt::{Arguments, Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-/// The physical type of an X52 device
-#[derive(PartialEq, Eq)]
-pub enum X52DeviceType {
-	/// The X52 Pro, with more features
-	X52Pro,
-	/// The standard X52
-	X52,
-}
-
-/// The color options for each LED
-pub enum X52ColoredLedStatus {
-	/// Turns the LED off
-	Off,
-	/// Sets the LED green
-	Green,
-	/// Sets the LED red
-	Red,
-	/// Sets the LED amber
-	Amber,
-}
-
-/// The options for an LED which can only be on or off
-pub enum X52OnOffLedStatus {
-	/// Turns the LED off
-	Off,
-	/// Turns the LED on
-	On,
-}
-
-/// The colored LEDs on the X52
-pub enum X52ColoredLed {
-	/// The A button on the stick
-	A,
-	/// The B button on the stick
-	B,
-	/// The D button on the throttle
-	D,
-	/// The E button on the throttle
-	E,
-	/// The LED between the T1 and T2 switches
-	T1,
-	/// The LED between the T3 and T4 switches
-	T3,
-	/// The LED between the T5 and T6 switches
-	T5,
-	/// The LED in the middle of the POV hat
-	PovHat,
-	/// The clutch button on the throttle (i button)
-	Clutch,
-}
-
-/// The on/off LEDs on the X52
-pub enum X52OnOffLed {
-	/// The fire button on the stick
-	Fire,
-	/// The LED inside the throttle
-	Throttle,
-}
-
-/// The line of the MFD
-pub enum X52MFDLine {
-	/// The first line
-	Line1,
-	/// The second line
-	Line2,
-	/// The third line
-	Line3,
-}
-
-/// The date format for the MFD
-pub enum X52DateFormat {
-	/// Day, month, year
-	DDMMYY,
-	/// Month, day, year
-	MMDDYY,
-	/// Year, month, day
-	YYMMDD,
-}
-
-/// The clock format for the MFD
-pub enum X52ClockFormat {
-	/// Twelve hour clock
-	Hr12,
-	/// 24 hour clock
-	Hr24,
-}
-
-/// The clocks on the X52
-pub enum X52Clocks {
-	/// Clock 1 is the real clock
-	Clock1,
-	/// Clock 2 is an offset from clock 1
-	Clock2,
-	/// Clock 3 is an offset from clock 1
-	Clock3,
-}
-
-impl Display for X52DeviceType {
-	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-		write!(
-			fmt,
-			"{}",
-			if self == &Self::X52 { "X52" } else { "X52 Pro" }
-		)
-	}
-}
+#[cfg(feature = "tokio")]
+mod async_driver;
+mod capture;
+mod command;
+mod decode;
+mod device;
+mod diagnostics;
+#[cfg(target_os = "linux")]
+mod hidraw;
+mod hotplug;
+mod info;
+#[cfg(unix)]
+mod lock;
+#[cfg(feature = "log")]
+mod logging;
+mod manager;
+pub mod protocol;
+mod queue;
+mod registry;
+mod resilient;
+mod retry;
+mod simulator;
+mod state;
+mod transport;
+mod vendor;
+
+#[cfg(feature = "tokio")]
+pub use async_driver::{X52AsyncDriver, X52EventStream};
+pub use capture::X52Capture;
+pub use command::X52Command;
+pub use decode::{decode_pcap, decode_usbmon_text, X52DecodedRequest};
+pub use device::{get_devices, X52DeviceEntry, X52DeviceId};
+#[cfg(target_os = "linux")]
+pub use hidraw::{get_hidraw_devices, HidrawTransport, X52HidrawEntry};
+pub use hotplug::{X52HotplugEvent, X52HotplugWatcher};
+pub use info::{X52DeviceInfo, X52EndpointInfo, X52InterfaceInfo};
+#[cfg(unix)]
+pub use lock::X52Lock;
+pub use manager::{X52Manager, X52Target, X52TargetResults};
+pub use queue::X52CommandQueue;
+pub use registry::{
+	known_descriptors, register_descriptor, register_descriptors,
+	register_descriptors_from_file,
+};
+pub use resilient::X52ResilientDriver;
+pub use retry::{X52FailedStep, X52RetryPolicy};
+pub use rx52_core::{
+	X52Capabilities, X52Capability, X52ClockFormat, X52Clocks, X52ColoredLed,
+	X52ColoredLedStatus, X52DateFormat, X52DeviceType, X52Hat, X52InputReport,
+	X52MFDLine, X52OnOffLed, X52OnOffLedStatus, X52ReportFormat,
+	X52VendorCommand,
+};
+pub use simulator::X52Simulator;
+pub use state::{X52Shadow, X52State};
+pub use transport::{MockTransport, UsbTransport, X52Transport};
 
 /// USB descriptor for a certain model of X52
 ///
@@ -137,12 +85,14 @@ impl Display for X52DeviceType {
 /// # Examples
 ///
 /// ```no_run
-/// let device = rx52::get_devices()[0];
-/// println!("Detected a {}", device.x52_type());
+/// let devices = rx52::get_devices().unwrap();
+/// println!("Detected a {}", devices[0].descriptor().x52_type());
 /// ```
 ///
-/// <div class="warning">For performance, this struct has all possible
-/// permutations created at compile time.</div>
+/// <div class="warning">For performance, this struct has all known
+/// permutations created at compile time. X52s with other IDs can be added
+/// with [`register_descriptor`].</div>
+#[derive(Debug)]
 pub struct X52Descriptor {
 	x52_type: &'static X52DeviceType,
 	vendor: &'static u16,
@@ -172,7 +122,11 @@ impl X52Descriptor {
 	}
 
 	fn eq_descriptor(&self, other: &DeviceDescriptor) -> bool {
-		self.vendor == &other.vendor_id() && self.product == &other.product_id()
+		self.eq_ids(other.vendor_id(), other.product_id())
+	}
+
+	fn eq_ids(&self, vendor: u16, product: u16) -> bool {
+		*self.vendor == vendor && *self.product == product
 	}
 
 	// fn eq_descriptor_optional(&self, other: &Option<DeviceDescriptor>) -> bool {
@@ -199,6 +153,9 @@ pub enum ErrorId {
 	/// No X52s were detected
 	NoX52sFound,
 	/// Tried to use a Pro feature on a regular X52
+	///
+	/// No longer returned by the driver, which returns
+	/// [`ErrorId::Unsupported`] instead.
 	NotAPro,
 	/// The device at the given bus and address is not an X52
 	DeviceNotX52,
@@ -208,14 +165,49 @@ pub enum ErrorId {
 	MFDLineTooLong,
 	/// Tried to write a string with non-ASCII characters to the MFD
 	MFDNotASCII,
-	/// The given offset for clocks 2 or 3 was greater than 24 hours
+	/// The given offset for clocks 2 or 3 was more than 1023 minutes either
+	/// way, the most the X52 can show
 	ClockOffsetTooBig,
+	/// The transport does not support the requested operation
+	NotSupported,
+	/// The given string could not be parsed as an [`X52DeviceId`]
+	InvalidDeviceId,
+	/// The given [`X52DeviceId`] did not match any detected X52s
+	DeviceIdNotFound,
+	/// The X52 was unplugged or reset, and has to be found again
+	DeviceDisconnected,
+	/// No X52 or group of X52s has been given the requested name
+	UnknownTarget,
+	/// The current user is not allowed to open the X52
+	///
+	/// See [`Error::hint`] for how to grant access.
+	PermissionDenied,
+	/// The X52 is in use by another program or process
+	DeviceBusy,
+	/// A kernel driver is bound to the X52 and could not be detached
+	KernelDriverActive,
+	/// Another process holds the [`X52Lock`] of the X52
+	DeviceLocked,
+	/// The given capture of USB traffic could not be read
+	InvalidCapture,
+	/// The given vendor requests could not be turned back into commands
+	UndecodableRequests,
+	/// An input report from the X52 was too short to parse
+	InvalidReport,
+	/// A line of a descriptor file could not be parsed, see
+	/// [`register_descriptors`]
+	InvalidDescriptor,
+	/// The X52 doesn't have the capability an operation needs
+	///
+	/// See [`Error::capability`] for which one.
+	Unsupported,
 }
 
 /// Some possible sources for ['Error']
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum ErrSources {
 	Rusb(rusb::Error),
+	Io(Arc<std::io::Error>),
 }
 
 #[derive(Debug)]
@@ -226,6 +218,17 @@ pub struct Error {
 	maybe_id: Option<ErrorId>,
 	source: Option<ErrSources>,
 	msg: String,
+	details: Option<Box<ErrorDetails>>,
+}
+
+/// Extra information only some [`Error`]s have, boxed to keep [`Error`]
+/// small
+#[derive(Debug, Default)]
+struct ErrorDetails {
+	device_path: Option<PathBuf>,
+	hint: Option<String>,
+	failed_step: Option<X52FailedStep>,
+	capability: Option<X52Capability>,
 }
 
 impl Error {
@@ -235,6 +238,7 @@ impl Error {
 			maybe_id: Some(id),
 			source: None,
 			msg: string,
+			details: None,
 		}
 	}
 
@@ -251,28 +255,102 @@ impl Error {
 	///
 	/// If this type is [`None`], then the [`Error`] originated from another
 	/// crate, probably `rusb`. You can try [`Error::source`] to get the
-	/// underlying error.
+	/// underlying error. Some `rusb` errors, such as the device going away,
+	/// are common enough to be given their own [`ErrorId`].
 	pub fn id(&self) -> Option<ErrorId> {
 		self.maybe_id
 	}
 
+	/// The device node the error happened on, like `/dev/bus/usb/001/004`
+	pub fn device_path(&self) -> Option<&Path> {
+		self.details.as_ref()?.device_path.as_deref()
+	}
+
+	/// Which request of a multi-request operation failed
+	///
+	/// Set for every error from sending a request to the X52, see
+	/// [`X52FailedStep`].
+	pub fn failed_step(&self) -> Option<X52FailedStep> {
+		self.details.as_ref()?.failed_step
+	}
+
+	/// The capability the X52 was missing, for [`ErrorId::Unsupported`]
+	pub fn capability(&self) -> Option<X52Capability> {
+		self.details.as_ref()?.capability
+	}
+
+	/// Records which request of an operation this error happened on
+	fn with_failed_step(mut self, step: X52FailedStep) -> Self {
+		if step.steps > 1 {
+			self.msg = format!(
+				"{} (request {} of {})",
+				self.msg,
+				step.step + 1,
+				step.steps
+			);
+		}
+		self.details_mut().failed_step = Some(step);
+		self
+	}
+
+	/// The details of the error, creating them if needed
+	fn details_mut(&mut self) -> &mut ErrorDetails {
+		self.details.get_or_insert_with(Default::default)
+	}
+
+	/// A suggestion on how to fix the error, if there is one
+	///
+	/// For example, the udev rule needed to grant access to an X52, or which
+	/// process is holding on to it.
+	pub fn hint(&self) -> Option<&str> {
+		self.details.as_ref()?.hint.as_deref()
+	}
+
 	/// Gets a [`rusb::Error`] from this [`Error`]
 	///
 	/// If this [`Error`] was generated by an exception in `rusb`, then this
 	/// method will return [`Some`] with the [`rusb::Error`]
 	pub fn rusb_error(&self) -> Option<rusb::Error> {
-		self.source.map(|x| match x {
-			ErrSources::Rusb(x) => x,
-		})
+		match self.source {
+			Some(ErrSources::Rusb(x)) => Some(x),
+			_ => None,
+		}
+	}
+
+	/// Gets a [`std::io::Error`] from this [`Error`]
+	///
+	/// If this [`Error`] was caused by a failed system call outside of
+	/// `rusb`, such as reading from a hidraw node, then this method will
+	/// return [`Some`] with the [`std::io::Error`]
+	pub fn io_error(&self) -> Option<&std::io::Error> {
+		match &self.source {
+			Some(ErrSources::Io(x)) => Some(x),
+			_ => None,
+		}
 	}
 }
 
 impl From<rusb::Error> for Error {
 	fn from(err: rusb::Error) -> Self {
 		Self {
-			maybe_id: None,
+			maybe_id: match err {
+				rusb::Error::NoDevice => Some(ErrorId::DeviceDisconnected),
+				_ => None,
+			},
 			source: Some(ErrSources::Rusb(err)),
 			msg: err.to_string(),
+			details: None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Self {
+			maybe_id: None,
+			msg: err.to_string(),
+			source: Some(ErrSources::Io(Arc::new(err))),
+			details: None,
 		}
 	}
 }
@@ -283,13 +361,18 @@ impl From<&str> for Error {
 			maybe_id: None,
 			source: None,
 			msg: string.to_string(),
+			details: None,
 		}
 	}
 }
 
 impl Display for Error {
 	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-		write!(fmt, "{}", self.msg)
+		write!(fmt, "{}", self.msg)?;
+		if let Some(hint) = self.hint() {
+			write!(fmt, "\n{hint}")?;
+		}
+		Ok(())
 	}
 }
 
@@ -299,6 +382,7 @@ impl ErrorTrait for Error {
 		match self.source {
 			Some(ref x) => match x {
 				ErrSources::Rusb(ref y) => Some(y),
+				ErrSources::Io(ref y) => Some(y.as_ref()),
 			},
 			None => None,
 		}
@@ -307,13 +391,28 @@ impl ErrorTrait for Error {
 
 const SAITEK_ID: u16 = 0x06A3;
 
-const POSSIBLE_DESCRIPTORS: [X52Descriptor; 3] = [
+/// Every X52 known to rx52
+///
+/// 0x0255, 0x075C and 0x0762 are the IDs listed by libx52 and by the Linux
+/// kernel (`USB_DEVICE_ID_SAITEK_X52_2`, `USB_DEVICE_ID_SAITEK_X52` and
+/// `USB_DEVICE_ID_SAITEK_X52_PRO` in `drivers/hid/hid-ids.h`). Logitech
+/// branded X52s which kept Saitek's IDs are covered by these. No other IDs
+/// are documented, so any other revision has to be added with
+/// [`register_descriptor`].
+static POSSIBLE_DESCRIPTORS: [X52Descriptor; 4] = [
+	// Kept from the first versions of rx52
 	X52Descriptor {
 		x52_type: &X52DeviceType::X52,
 		vendor: &SAITEK_ID,
 		product: &0x0225,
 		description: "X52 Flight Controller",
 	},
+	X52Descriptor {
+		x52_type: &X52DeviceType::X52,
+		vendor: &SAITEK_ID,
+		product: &0x0255,
+		description: "X52 Flight Controller",
+	},
 	X52Descriptor {
 		x52_type: &X52DeviceType::X52,
 		vendor: &SAITEK_ID,
@@ -328,43 +427,33 @@ const POSSIBLE_DESCRIPTORS: [X52Descriptor; 3] = [
 	},
 ];
 
-/// The ID used to make vendor requests
-const X52_VENDOR_REQUEST: u8 = 0x91;
-/// The timeout (in milliseconds) for vendor requests
-const REQUEST_TIMEOUT_MILLIS: Duration = Duration::from_millis(5000);
-/// The command used to set an LED
-const LED_SET_COMMAND: u16 = 0xB8;
-/// The command to set the brightness of the LEDs
-const LED_SET_BRIGHTNESS_COMMAND: u16 = 0xB2;
-/// The command to set the brightness of the MFD
-const MFD_SET_BRIGHTNESS_COMMAND: u16 = 0xB1;
-/// The command used to clear a line of the MFD
-const MFD_CLEAR_LINE_COMMAND: u16 = 0x08;
-/// The amount of text that can fit on a line
-const MFD_LINE_SIZE: usize = 16;
-/// The command to set the shift indicator on the MFD
-const SET_SHIFT_STATUS_COMMAND: u16 = 0xFD;
-/// The command to set the blinking of the throttle and POV hat LEDs
-const SET_BLINK_STATUS_COMMAND: u16 = 0xB4;
-/// The command to set clock 1
-const CLOCK_1_SET_COMMAND: u16 = 0xC0;
-/// The command to set clock 2's offset from clock 1
-const CLOCK_2_OFFSET_COMMAND: u16 = 0xC1;
-/// The command to set clock 3's offset from clock 1
-const CLOCK_3_OFFSET_COMMAND: u16 = 0xC2;
-/// Sets the day and month on the MFD
-const SET_DAY_MONTH_COMMAND: u16 = 0xC4;
-/// Sets the year on the MFD
-const SET_YEAR_COMMAND: u16 = 0xC8;
-
 /// Returns true if the given descriptor refers to an X52
 fn is_descriptor_x52(descriptor: &DeviceDescriptor) -> bool {
-	POSSIBLE_DESCRIPTORS
-		.iter()
+	registry::descriptors()
+		.into_iter()
 		.any(|x| x.eq_descriptor(descriptor))
 }
 
-/// Returns Ok(()) if the given descriptor is an x52, or a generic error
+/// Finds the X52 descriptor matching the given USB descriptor
+fn find_descriptor(
+	descriptor: &DeviceDescriptor,
+) -> Option<&'static X52Descriptor> {
+	registry::descriptors()
+		.into_iter()
+		.find(|x| x.eq_descriptor(descriptor))
+}
+
+/// Finds the X52 descriptor with the given vendor and product IDs
+fn find_descriptor_by_ids(
+	vendor: u16,
+	product: u16,
+) -> Option<&'static X52Descriptor> {
+	registry::descriptors()
+		.into_iter()
+		.find(|x| x.eq_ids(vendor, product))
+}
+
+// Returns Ok(()) if the given descriptor is an x52, or a generic error
 // fn is_descriptor_x52_or_error(
 // 	descriptor: &DeviceDescriptor,
 // ) -> Result<(), Error> {
@@ -397,8 +486,8 @@ fn find_device_from_bus_device(
 fn get_x52_type_from_descriptor(
 	descriptor: &DeviceDescriptor,
 ) -> Result<&'static X52DeviceType, Error> {
-	POSSIBLE_DESCRIPTORS
-		.iter()
+	registry::descriptors()
+		.into_iter()
 		.find(|x| x.eq_descriptor(descriptor))
 		.map(|x| x.x52_type)
 		.ok_or(Error::new(
@@ -411,141 +500,171 @@ fn get_x52_type_from_descriptor(
 		))
 }
 
-// Ensure that an X52 is a pro or return with an error
-fn ensure_x52_is_pro(driver: &X52Driver) -> Result<(), Error> {
-	if *driver.x52_type()? == X52DeviceType::X52 {
-		Err(Error::new(
-			ErrorId::NotAPro,
-			format!(
-				"The device at Bus {:03} Device {:03} is not an X52 Pro",
-				driver.get_bus_device().0,
-				driver.get_bus_device().1
-			),
-		))
-	} else {
-		Ok(())
-	}
+/// A driver used to control a X52 device
+///
+/// By default the driver talks to the X52 through libusb, but it can be used
+/// over any [`X52Transport`].
+pub struct X52Driver<T: X52Transport = UsbTransport> {
+	transport: T,
+	x52_type: &'static X52DeviceType,
+	retry_policy: X52RetryPolicy,
+	capture: Option<Mutex<X52Capture>>,
 }
 
-/// Does a vendor command on the given device handle
-fn do_vendor_command(
-	device: &DeviceHandle<Context>,
-	index: u16,
-	value: u16,
-) -> Result<(), Error> {
-	device.write_control(
-		request_type(Direction::Out, RequestType::Vendor, Recipient::Device),
-		X52_VENDOR_REQUEST,
-		value,
-		index,
-		&[0_u8; 0], // Empty data
-		REQUEST_TIMEOUT_MILLIS,
-	)?;
-	Ok(())
-}
+impl<T: X52Transport> X52Driver<T> {
+	/// Creates an X52Driver which sends its commands over `transport`
+	pub fn new(transport: T, x52_type: &'static X52DeviceType) -> Self {
+		Self {
+			transport,
+			x52_type,
+			retry_policy: X52RetryPolicy::default(),
+			capture: None,
+		}
+	}
 
-/// Maps a given [X52OnOffLed] to a value to be sent to the X52
-fn map_on_off_led_to_value(led: &X52OnOffLed) -> u8 {
-	match led {
-		X52OnOffLed::Fire => 1,
-		X52OnOffLed::Throttle => 20,
+	/// The policy used for timeouts and retries of vendor requests
+	pub fn retry_policy(&self) -> &X52RetryPolicy {
+		&self.retry_policy
 	}
-}
 
-/// Maps a given [X52ColoredLed] to the red and green values to be sent to the X52
-fn map_colored_led_to_value(led: &X52ColoredLed) -> (u8, u8) {
-	match led {
-		X52ColoredLed::A => (2, 3),
-		X52ColoredLed::B => (4, 5),
-		X52ColoredLed::D => (6, 7),
-		X52ColoredLed::E => (8, 9),
-		X52ColoredLed::T1 => (10, 11),
-		X52ColoredLed::T3 => (12, 13),
-		X52ColoredLed::T5 => (14, 15),
-		X52ColoredLed::PovHat => (16, 17),
-		X52ColoredLed::Clutch => (18, 19),
+	/// Changes the policy used for timeouts and retries of vendor requests
+	pub fn set_retry_policy(&mut self, policy: X52RetryPolicy) {
+		self.transport.set_timeout(policy.timeout);
+		self.retry_policy = policy;
 	}
-}
 
-/// Maps a given [X52OnOffLedStatus] to the values needed to send to the X52
-fn map_on_off_led_status_to_value(status: &X52OnOffLedStatus) -> u8 {
-	match status {
-		X52OnOffLedStatus::Off => 0,
-		X52OnOffLedStatus::On => 1,
+	/// Starts recording every request sent and report read to `capture`
+	///
+	/// Replaces any capture already running, which is finished first.
+	pub fn start_capture(&mut self, capture: X52Capture) {
+		if let Some(old) = self.stop_capture() {
+			let _ = old.finish();
+		}
+		self.capture = Some(Mutex::new(capture));
 	}
-}
 
-/// Maps a given [X52ColoredLedStatus] to the values needed for the red and green LEDs
-fn map_colored_led_status_to_value(status: &X52ColoredLedStatus) -> (u8, u8) {
-	match status {
-		// Both off
-		X52ColoredLedStatus::Off => (0, 0),
-		// Red on
-		X52ColoredLedStatus::Red => (1, 0),
-		// Green on
-		X52ColoredLedStatus::Green => (0, 1),
-		// Both on
-		X52ColoredLedStatus::Amber => (1, 1),
+	/// Stops recording, giving back the capture so it can be finished
+	pub fn stop_capture(&mut self) -> Option<X52Capture> {
+		self.capture
+			.take()
+			.map(|x| x.into_inner().unwrap_or_else(|x| x.into_inner()))
 	}
-}
 
-/// Maps a given [X52MFDLine] to the values needed to send to the X52
-fn map_mfd_line_to_value(line: &X52MFDLine) -> u8 {
-	match line {
-		X52MFDLine::Line1 => 0xD1,
-		X52MFDLine::Line2 => 0xD2,
-		X52MFDLine::Line3 => 0xD4,
+	/// Hands the running capture, if any, to `record`
+	fn record<F: FnOnce(&mut X52Capture, (u8, u8))>(&self, record: F) {
+		if let Some(capture) = &self.capture {
+			let bus_device = self.transport.bus_device().unwrap_or((0, 0));
+			record(
+				&mut capture.lock().unwrap_or_else(|x| x.into_inner()),
+				bus_device,
+			);
+		}
 	}
-}
 
-/// Maps booleans to values needed to send to the X52
-fn map_bool_to_value(enabled: bool) -> u16 {
-	match enabled {
-		true => 0x51,
-		false => 0x50,
+	/// Encodes `command` and sends its requests, see
+	/// [`X52Driver::send_requests`]
+	fn send_command(
+		&self,
+		operation: Arguments<'_>,
+		command: &X52Command,
+	) -> Result<(), Error> {
+		self.ensure_supported(command.capability())?;
+		self.send_vendor_commands(operation, &protocol::encode(command)?)
+	}
+
+	/// Fails with [`ErrorId::Unsupported`] if the X52 doesn't have
+	/// `capability`
+	fn ensure_supported(&self, capability: X52Capability) -> Result<(), Error> {
+		if self.supports(capability) {
+			return Ok(());
+		}
+		let device = match self.transport.bus_device() {
+			Some((bus, device)) => {
+				format!(
+					"The {} at Bus {bus:03} Device {device:03}",
+					self.x52_type
+				)
+			}
+			None => format!("The {}", self.x52_type),
+		};
+		let mut err = Error::new(
+			ErrorId::Unsupported,
+			format!("{device} doesn't support {capability}"),
+		);
+		err.details_mut().capability = Some(capability);
+		Err(err)
 	}
-}
 
-/// Writes a line to the MFD
-/// This does not check if the string is ASCII!!! Text must be 16 bytes!!!
-fn write_mfd_line(
-	handle: &DeviceHandle<Context>,
-	line: &X52MFDLine,
-	text: &str,
-) -> Result<(), Error> {
-	if text.len() == 0 {
+	/// Sends the requests for some commands from the catalogue, see
+	/// [`X52Driver::send_requests`]
+	fn send_vendor_commands(
+		&self,
+		operation: Arguments<'_>,
+		commands: &[X52VendorCommand],
+	) -> Result<(), Error> {
+		let requests = commands.iter().map(|x| x.encode()).collect::<Vec<_>>();
+		self.send_requests(operation, &requests)
+	}
+
+	/// Sends the vendor requests making up one operation, in order
+	///
+	/// Each request is retried according to the retry policy. If one still
+	/// fails, the rest are not sent and the error says which one failed.
+	/// `operation` describes the call which made the requests, for logging.
+	fn send_requests(
+		&self,
+		operation: Arguments<'_>,
+		requests: &[(u16, u16)],
+	) -> Result<(), Error> {
+		#[cfg(feature = "log")]
+		logging::log_operation(operation, requests);
+		#[cfg(not(feature = "log"))]
+		let _ = operation;
+		for (step, &(index, value)) in requests.iter().enumerate() {
+			#[cfg(feature = "log")]
+			let start = std::time::Instant::now();
+			let result = self.retry_policy.run(|| {
+				let result = self.transport.vendor_command(index, value);
+				self.record(|capture, bus_device| {
+					capture.record_vendor_request(
+						bus_device, index, value, &result,
+					)
+				});
+				result
+			});
+			#[cfg(feature = "log")]
+			logging::log_request(index, value, start.elapsed(), &result);
+			result.map_err(|err| {
+				err.with_failed_step(X52FailedStep {
+					step,
+					steps: requests.len(),
+					index,
+					value,
+				})
+			})?;
+		}
 		Ok(())
-	} else {
-		do_vendor_command(
-			handle,
-			map_mfd_line_to_value(line) as u16,
-			(text.as_bytes()[1] as u16) << 8 | text.as_bytes()[0] as u16,
-		)?;
-		write_mfd_line(handle, line, &text[2..])
 	}
-}
 
-/// A driver used to control a X52 device
-pub struct X52Driver {
-	device: Device<Context>,
-	// The context needs to be kept alive here
-	_usb_context: Context,
-}
+	/// The transport this driver sends its commands over
+	pub fn transport(&self) -> &T {
+		&self.transport
+	}
+
+	/// Gives the transport back, consuming the driver
+	pub fn into_transport(self) -> T {
+		self.transport
+	}
 
-impl X52Driver {
 	/// Toggles an LED which can be either on or off on the X52
 	pub fn toggle_led_on_off(
 		&self,
 		led: &X52OnOffLed,
 		status: &X52OnOffLedStatus,
 	) -> Result<(), Error> {
-		ensure_x52_is_pro(self)?;
-		let handle = self.device.open()?;
-		do_vendor_command(
-			&handle,
-			LED_SET_COMMAND,
-			((map_on_off_led_to_value(led) as u16) << 8)
-				+ map_on_off_led_status_to_value(status) as u16,
+		self.send_command(
+			format_args!("set LED {led:?} to {status:?}"),
+			&X52Command::SetOnOffLed(*led, *status),
 		)
 	}
 
@@ -555,28 +674,17 @@ impl X52Driver {
 		led: &X52ColoredLed,
 		status: &X52ColoredLedStatus,
 	) -> Result<(), Error> {
-		ensure_x52_is_pro(self)?;
-		let handle = self.device.open()?;
-		do_vendor_command(
-			&handle,
-			LED_SET_COMMAND,
-			((map_colored_led_to_value(led).0 as u16) << 8)
-				+ map_colored_led_status_to_value(status).0 as u16,
-		)?;
-		do_vendor_command(
-			&handle,
-			LED_SET_COMMAND,
-			((map_colored_led_to_value(led).1 as u16) << 8)
-				+ map_colored_led_status_to_value(status).1 as u16,
+		self.send_command(
+			format_args!("set LED {led:?} to {status:?}"),
+			&X52Command::SetColoredLed(*led, *status),
 		)
 	}
 
 	/// Clears a line of text on the MFD
 	pub fn clear_mfd_line(&self, line: &X52MFDLine) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			MFD_CLEAR_LINE_COMMAND | map_mfd_line_to_value(line) as u16,
-			0,
+		self.send_command(
+			format_args!("clear MFD {line:?}"),
+			&X52Command::ClearMfdLine(*line),
 		)
 	}
 
@@ -589,20 +697,10 @@ impl X52Driver {
 		line: &X52MFDLine,
 		text: String,
 	) -> Result<(), Error> {
-		if !text.is_ascii() {
-			return Err(Error::new(
-				ErrorId::MFDNotASCII,
-				format!("The text \"{text}\" contains non-ASCII characters"),
-			));
-		}
-		if text.len() > MFD_LINE_SIZE {
-			return Err(Error::new(
-				ErrorId::MFDLineTooLong,
-				format!("The text \"{text}\" is too long to fit on the MFD"),
-			));
-		}
-		self.clear_mfd_line(line)?;
-		write_mfd_line(&self.device.open()?, line, &format!("{:^16}", text))
+		self.send_command(
+			format_args!("set MFD {line:?} to {text:?}"),
+			&X52Command::SetMfdText(*line, text.clone()),
+		)
 	}
 
 	/// Sets the brightness of the LEDs on the X52
@@ -610,10 +708,9 @@ impl X52Driver {
 	/// `brightness` should be between 0 and 128. Anything higher can cause "unintended effects", says
 	/// [libx52](https://nirenjan.github.io/libx52/group__libx52mfdled.html#ga9bbf5e1ff83201f6124b2d3c75c837c6).
 	pub fn set_led_brightness(&self, brightness: u8) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			LED_SET_BRIGHTNESS_COMMAND,
-			brightness as u16,
+		self.send_command(
+			format_args!("set LED brightness to {brightness}"),
+			&X52Command::SetLedBrightness(brightness),
 		)
 	}
 
@@ -622,28 +719,25 @@ impl X52Driver {
 	/// `brightness` should be between 0 and 128. Anything higher can cause "unintended effects", says
 	/// [libx52](https://nirenjan.github.io/libx52/group__libx52mfdled.html#ga9bbf5e1ff83201f6124b2d3c75c837c6).
 	pub fn set_mfd_brightness(&self, brightness: u8) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			MFD_SET_BRIGHTNESS_COMMAND,
-			brightness as u16,
+		self.send_command(
+			format_args!("set MFD brightness to {brightness}"),
+			&X52Command::SetMfdBrightness(brightness),
 		)
 	}
 
 	/// Sets the "shift" status on the X52's MFD
 	pub fn set_shift_status(&self, enabled: bool) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			SET_SHIFT_STATUS_COMMAND,
-			map_bool_to_value(enabled),
+		self.send_command(
+			format_args!("set shift status to {enabled}"),
+			&X52Command::SetShiftStatus(enabled),
 		)
 	}
 
 	/// Sets the blink status for the throttle and POV hat
 	pub fn set_blink_status(&self, enabled: bool) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			SET_BLINK_STATUS_COMMAND,
-			map_bool_to_value(enabled),
+		self.send_command(
+			format_args!("set blink status to {enabled}"),
+			&X52Command::SetBlinkStatus(enabled),
 		)
 	}
 
@@ -654,12 +748,13 @@ impl X52Driver {
 		minute: u8,
 		use_24h: bool,
 	) -> Result<(), Error> {
-		do_vendor_command(
-			&self.device.open()?,
-			CLOCK_1_SET_COMMAND,
-			(use_24h as u16) << 15
-				| ((hour as u16) & 0x7F) << 8
-				| minute as u16,
+		self.send_command(
+			format_args!("set clock 1 to {hour:02}:{minute:02}"),
+			&X52Command::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			},
 		)
 	}
 
@@ -669,24 +764,10 @@ impl X52Driver {
 		offset: i16,
 		use_24h: bool,
 	) -> Result<(), Error> {
-		// Limit offset to 24 hours either direction
-		if offset < -1440 || offset > 1440 {
-			Err(Error::new(
-				ErrorId::ClockOffsetTooBig,
-				format!("Clock 2 offset ({offset}) too large"),
-			))
-		} else {
-			do_vendor_command(
-				&self.device.open()?,
-				CLOCK_2_OFFSET_COMMAND,
-				(use_24h as u16) << 15
-					| if offset > 0 {
-						1_u16 << 10 | -offset as u16
-					} else {
-						offset as u16
-					},
-			)
-		}
+		self.send_command(
+			format_args!("set clock 2 offset to {offset} minutes"),
+			&X52Command::SetClock2Offset { offset, use_24h },
+		)
 	}
 
 	/// Sets the clock 3 offset in minutes from clock 1
@@ -695,49 +776,120 @@ impl X52Driver {
 		offset: i16,
 		use_24h: bool,
 	) -> Result<(), Error> {
-		// Limit offset to 24 hours either direction
-		if offset < -1440 || offset > 1440 {
-			Err(Error::new(
-				ErrorId::ClockOffsetTooBig,
-				format!("Clock 3 offset ({offset}) too large"),
-			))
-		} else {
-			do_vendor_command(
-				&self.device.open()?,
-				CLOCK_3_OFFSET_COMMAND,
-				(use_24h as u16) << 15
-					| if offset > 0 {
-						1_u16 << 10 | -offset as u16
-					} else {
-						offset as u16
-					},
-			)
-		}
+		self.send_command(
+			format_args!("set clock 3 offset to {offset} minutes"),
+			&X52Command::SetClock3Offset { offset, use_24h },
+		)
+	}
+
+	/// Sets the given day, month, and year as they day on the X52
+	/// Year must only be two digits
+	pub fn set_date(
+		&self,
+		day: u8,
+		month: u8,
+		year: u8,
+		format: X52DateFormat,
+	) -> Result<(), Error> {
+		self.send_command(
+			format_args!(
+				"set date to {day:02}/{month:02}/{year:02} {format:?}"
+			),
+			&X52Command::SetDate {
+				day,
+				month,
+				year,
+				format,
+			},
+		)
 	}
 
-    /// Sets the given day, month, and year as they day on the X52
-    /// Year must only be two digits
-    pub fn set_date(&self, day: u8, month: u8, year: u8, format: X52DateFormat) -> Result<(), Error> {
-        do_vendor_command(&self.device.open()?, SET_DAY_MONTH_COMMAND, match format {
-            X52DateFormat::DDMMYY => (month as u16) << 8 | day as u16,
-            X52DateFormat::MMDDYY => (day as u16) << 8 | month as u16,
-            X52DateFormat::YYMMDD => (month as u16) << 8 | year as u16,
-        })?;
-        do_vendor_command(&self.device.open()?, SET_YEAR_COMMAND, match format {
-            X52DateFormat::DDMMYY => year as u16,
-            X52DateFormat::MMDDYY => year as u16,
-            X52DateFormat::YYMMDD => day as u16,
-        })
-    }
+	/// Reads a raw input report from the X52 into `buf`
+	///
+	/// Returns the number of bytes read, or an error if nothing arrived
+	/// before `timeout`.
+	pub fn read_report(
+		&self,
+		buf: &mut [u8],
+		timeout: Duration,
+	) -> Result<usize, Error> {
+		let len = self.transport.read_report(buf, timeout)?;
+		self.record(|capture, bus_device| {
+			capture.record_report(bus_device, &buf[..len])
+		});
+		Ok(len)
+	}
+
+	/// Reads and parses an input report from the X52
+	///
+	/// Fails with [`ErrorId::InvalidReport`] if the X52 sent less than a
+	/// whole report.
+	pub fn read_input_report(
+		&self,
+		timeout: Duration,
+	) -> Result<X52InputReport, Error> {
+		let mut buf = [0; rx52_core::X52_PRO_REPORT_LEN];
+		let len = self.read_report(&mut buf, timeout)?;
+		X52InputReport::parse(*self.x52_type, &buf[..len]).map_err(|err| {
+			Error::new(
+				ErrorId::InvalidReport,
+				format!("{err}, only {len} bytes were read"),
+			)
+		})
+	}
 
 	/// Gets the type of X52 this device refers to
-	pub fn x52_type(&self) -> Result<&'static X52DeviceType, Error> {
-		get_x52_type_from_descriptor(&self.device.device_descriptor()?)
+	pub fn x52_type(&self) -> &'static X52DeviceType {
+		self.x52_type
 	}
 
+	/// Everything the X52 can do
+	///
+	/// Every method fails with [`ErrorId::Unsupported`] for a capability the X52
+	/// doesn't have, so check here first to hide what can't be used.
+	pub fn capabilities(&self) -> X52Capabilities {
+		self.x52_type.capabilities()
+	}
+
+	/// Whether the X52 has `capability`, see [`X52Driver::capabilities`]
+	pub fn supports(&self, capability: X52Capability) -> bool {
+		self.capabilities().supports(capability)
+	}
+}
+
+impl X52Driver<UsbTransport> {
 	/// Returns the (bus, device) of the device
 	pub fn get_bus_device(&self) -> (u8, u8) {
-		(self.device.bus_number(), self.device.address())
+		let device = self.transport.device();
+		(device.bus_number(), device.address())
+	}
+
+	/// The stable ID of the device
+	///
+	/// Save this rather than the bus and device number to find the same X52
+	/// again with [`X52Driver::new_from_id`].
+	pub fn id(&self) -> X52DeviceId {
+		let device = self.transport.device();
+		X52DeviceId::new(
+			device.bus_number(),
+			device.port_numbers().unwrap_or_default(),
+			self.transport.serial(),
+		)
+	}
+
+	/// Everything USB says about the X52
+	///
+	/// This is the manufacturer, product and serial strings, the firmware
+	/// revision, speed, port path, and the interfaces and endpoints, which is
+	/// what's worth attaching to a bug report. Fails if the descriptors of the
+	/// X52 can't be read, but not if it has no strings.
+	pub fn device_info(&self) -> Result<X52DeviceInfo, Error> {
+		self.transport.info()
+	}
+
+	/// Creates an X52Driver for the X52 with the given stable ID
+	pub fn new_from_id(id: &X52DeviceId) -> Result<X52Driver, Error> {
+		device::find_device_by_id(&Context::new()?, id)?.open()
 	}
 
 	/// Creates an X52Driver given a known bus and device number
@@ -748,11 +900,13 @@ impl X52Driver {
 		let context = Context::new()?;
 		let usb_device = find_device_from_bus_device(&context, bus, device)?;
 
-		if is_descriptor_x52(&usb_device.device_descriptor()?) {
-			Ok(Self {
-				device: usb_device,
-				_usb_context: context,
-			})
+		let descriptor = usb_device.device_descriptor()?;
+
+		if is_descriptor_x52(&descriptor) {
+			Ok(Self::new(
+				UsbTransport::open(usb_device)?,
+				get_x52_type_from_descriptor(&descriptor)?,
+			))
 		} else {
 			Err(Error::new(
 				ErrorId::DeviceNotX52,
@@ -766,24 +920,27 @@ impl X52Driver {
 }
 
 /// Gets a vector of available X52 devices without creating a driver for any
-pub fn get_possible_device_types() -> Result<Vec<&'static X52Descriptor>, Error> {
+pub fn get_possible_device_types() -> Result<Vec<&'static X52Descriptor>, Error>
+{
 	Ok(Context::new()?
 		.devices()?
 		.iter()
 		.filter_map(|x| x.device_descriptor().ok())
-		.filter_map(|x| {
-			POSSIBLE_DESCRIPTORS.iter().find(|y| y.eq_descriptor(&x))
-		})
+		.filter_map(|x| find_descriptor(&x))
 		.collect::<Vec<&'static X52Descriptor>>())
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use rx52_core::{
+		CLOCK_2_OFFSET_COMMAND, CLOCK_3_OFFSET_COMMAND, LED_SET_COMMAND,
+		SET_DAY_MONTH_COMMAND, SET_YEAR_COMMAND,
+	};
 
 	#[test]
 	fn possible_descriptors_ok() {
-		for i in POSSIBLE_DESCRIPTORS {
+		for i in &POSSIBLE_DESCRIPTORS {
 			assert_eq!(i.vendor(), &SAITEK_ID);
 		}
 	}
@@ -806,6 +963,130 @@ mod tests {
 		assert_eq!(err.to_string(), string)
 	}
 
+	fn mock_driver(
+		x52_type: &'static X52DeviceType,
+	) -> X52Driver<MockTransport> {
+		X52Driver::new(MockTransport::new(), x52_type)
+	}
+
+	#[test]
+	fn colored_led_sets_red_and_green() {
+		let driver = mock_driver(&X52DeviceType::X52Pro);
+		driver
+			.toggle_led_colored(&X52ColoredLed::A, &X52ColoredLedStatus::Red)
+			.unwrap();
+		assert_eq!(
+			driver.transport().requests(),
+			vec![(LED_SET_COMMAND, 0x0201), (LED_SET_COMMAND, 0x0300)]
+		);
+	}
+
+	#[test]
+	fn led_on_non_pro_fails() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		let err = driver
+			.toggle_led_on_off(&X52OnOffLed::Fire, &X52OnOffLedStatus::On)
+			.unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::Unsupported));
+		assert_eq!(err.capability(), Some(X52Capability::OnOffLeds));
+		assert!(driver.transport().requests().is_empty());
+	}
+
+	#[test]
+	fn capabilities_are_checked_everywhere() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		assert!(!driver.supports(X52Capability::IndividualLeds));
+		assert!(driver.supports(X52Capability::Mfd));
+		let err = driver
+			.send_vendor_command(&X52VendorCommand::SetLed { id: 1, on: true })
+			.unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::Unsupported));
+		assert_eq!(err.capability(), Some(X52Capability::IndividualLeds));
+		assert!(err.to_string().contains("doesn't support individual LEDs"));
+		driver.set_shift_status(true).unwrap();
+		assert_eq!(driver.transport().requests().len(), 1);
+	}
+
+	#[test]
+	fn mfd_text_is_cleared_then_centered() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		driver
+			.set_mfd_text(&X52MFDLine::Line2, "ABCD".to_string())
+			.unwrap();
+		let requests = driver.transport().requests();
+		assert_eq!(requests.len(), 9);
+		assert_eq!(requests[0], (0xDA, 0));
+		assert_eq!(requests[4], (0xD2, u16::from_le_bytes(*b"AB")));
+		assert!(requests[1..].iter().all(|x| x.0 == 0xD2));
+	}
+
+	#[test]
+	fn mfd_text_too_long() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		let err = driver
+			.set_mfd_text(&X52MFDLine::Line1, "x".repeat(17))
+			.unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::MFDLineTooLong));
+	}
+
+	#[test]
+	fn date_is_split_in_two_requests() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		driver.set_date(31, 12, 24, X52DateFormat::DDMMYY).unwrap();
+		assert_eq!(
+			driver.transport().requests(),
+			vec![(SET_DAY_MONTH_COMMAND, 0x0C1F), (SET_YEAR_COMMAND, 24)]
+		);
+	}
+
+	#[test]
+	fn clock_offsets_are_sign_and_magnitude() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		driver.set_clock_2_offset(90, false).unwrap();
+		driver.set_clock_3_offset(-1023, true).unwrap();
+		assert_eq!(
+			driver.transport().requests(),
+			vec![
+				(CLOCK_2_OFFSET_COMMAND, 90),
+				(CLOCK_3_OFFSET_COMMAND, 0x8000 | 0x0400 | 1023)
+			]
+		);
+	}
+
+	#[test]
+	fn clock_offsets_over_1023_fail() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		// A full day used to be accepted, but doesn't fit in 10 bits
+		for offset in [1024, -1024, 1440, -1440] {
+			let err = driver.set_clock_2_offset(offset, true).unwrap_err();
+			assert_eq!(err.id(), Some(ErrorId::ClockOffsetTooBig));
+		}
+		assert!(driver.transport().requests().is_empty());
+	}
+
+	#[test]
+	fn mock_reads_reports_in_order() {
+		let driver = mock_driver(&X52DeviceType::X52);
+		driver.transport().push_report(vec![1, 2, 3]);
+		let mut buf = [0_u8; 16];
+		let read = driver.read_report(&mut buf, Duration::ZERO).unwrap();
+		assert_eq!(&buf[..read], &[1, 2, 3]);
+		assert!(driver.read_report(&mut buf, Duration::ZERO).is_err());
+	}
+
+	#[test]
+	fn input_reports_are_parsed() {
+		let driver = mock_driver(&X52DeviceType::X52Pro);
+		let mut report = vec![0; rx52_core::X52_PRO_REPORT_LEN];
+		report[13] = 3 << 4;
+		driver.transport().push_report(report);
+		driver.transport().push_report(vec![0; 4]);
+		let report = driver.read_input_report(Duration::ZERO).unwrap();
+		assert_eq!(report.hat(), X52Hat::Right);
+		let err = driver.read_input_report(Duration::ZERO).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::InvalidReport));
+	}
+
 	#[test]
 	fn error_rusb_test() {
 		let err_rusb = rusb::Error::Busy;
@@ -813,4 +1094,38 @@ mod tests {
 		assert_eq!(err.rusb_error().unwrap(), err_rusb);
 		assert_eq!(err.to_string(), err_rusb.to_string())
 	}
+
+	#[test]
+	fn failed_step_is_reported() {
+		let driver = mock_driver(&X52DeviceType::X52Pro);
+		driver.transport().push_outcome(Ok(()));
+		driver.transport().push_outcome(Err(rusb::Error::Pipe));
+		let err = driver
+			.toggle_led_colored(&X52ColoredLed::A, &X52ColoredLedStatus::Red)
+			.unwrap_err();
+		let step = err.failed_step().unwrap();
+		assert_eq!((step.step(), step.steps()), (1, 2));
+		assert!(step.is_partial());
+		assert_eq!(driver.transport().requests().len(), 1);
+	}
+
+	#[test]
+	fn failed_requests_are_retried() {
+		let mut driver = mock_driver(&X52DeviceType::X52);
+		driver.set_retry_policy(X52RetryPolicy {
+			retries: 1,
+			backoff: Duration::ZERO,
+			..Default::default()
+		});
+		driver.transport().push_outcome(Err(rusb::Error::Timeout));
+		driver.set_mfd_brightness(10).unwrap();
+		assert_eq!(driver.transport().requests(), vec![(0xB1, 10)]);
+	}
+
+	#[test]
+	fn error_rusb_no_device() {
+		let err = Error::from(rusb::Error::NoDevice);
+		assert_eq!(err.id(), Some(ErrorId::DeviceDisconnected));
+		assert_eq!(err.rusb_error(), Some(rusb::Error::NoDevice));
+	}
 }
@@ -0,0 +1,245 @@
+//! Advisory locks which stop several processes writing to one X52 at once
+
+use crate::{Error, ErrorId, UsbTransport, X52DeviceId, X52Driver};
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait between attempts in [`X52Lock::acquire_timeout`]
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+/// Where lock files go on systems which have a shared lock directory
+const SYSTEM_LOCK_DIR: &str = "/run/lock";
+
+/// The directories lock files can be kept in, most preferred first, see
+/// [`X52Lock`]
+fn lock_dirs() -> Vec<PathBuf> {
+	let mut dirs = vec![PathBuf::from(SYSTEM_LOCK_DIR)];
+	if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+		if !dir.is_empty() {
+			dirs.push(PathBuf::from(dir));
+		}
+	}
+	dirs.push(std::env::temp_dir());
+	dirs
+}
+
+/// The name of the lock file for the X52 at the given bus and ports
+///
+/// Only the location is used, so every process agrees on the file even if
+/// some of them can't read the serial number.
+fn lock_file_name(id: &X52DeviceId) -> String {
+	let ports = id
+		.port_path()
+		.iter()
+		.map(|x| x.to_string())
+		.collect::<Vec<_>>()
+		.join(".");
+	format!("rx52-{}-{ports}.lock", id.bus())
+}
+
+/// Opens the lock file, creating it if needed
+fn open_lock_file(path: &Path) -> std::io::Result<File> {
+	let file = OpenOptions::new().create(true).append(true).open(path);
+	match file {
+		Ok(file) => Ok(file),
+		// Somebody else created the file without giving us write access,
+		// which locking doesn't need anyways
+		Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+			File::open(path)
+		}
+		Err(err) => Err(err),
+	}
+}
+
+/// Opens the lock file called `name` in the first of `dirs` which lets us
+fn open_lock_in(dirs: &[PathBuf], name: &str) -> Result<File, Error> {
+	let mut last_err = None;
+	for dir in dirs {
+		match open_lock_file(&dir.join(name)) {
+			Ok(file) => return Ok(file),
+			Err(err) => last_err = Some(err),
+		}
+	}
+	Err(match last_err {
+		Some(err) => err.into(),
+		None => Error::from("There is nowhere to keep lock files"),
+	})
+}
+
+/// Opens the lock file of the X52 with the given ID
+fn open_lock(id: &X52DeviceId) -> Result<File, Error> {
+	open_lock_in(&lock_dirs(), &lock_file_name(id))
+}
+
+/// Runs `flock` on `file` with the given operation
+fn flock(file: &File, operation: libc::c_int) -> std::io::Result<()> {
+	loop {
+		// SAFETY: the descriptor belongs to file, which outlives the call
+		if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+			return Ok(());
+		}
+		let err = std::io::Error::last_os_error();
+		if err.kind() != ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+}
+
+/// Exclusive access to an X52, shared between every process using rx52
+///
+/// The lock is advisory: it only keeps out other programs which take it as
+/// well, it doesn't stop anyone from writing to the X52. Take it around
+/// anything which must not be interleaved with other programs, such as
+/// writing a whole MFD line, which is sent two characters at a time.
+///
+/// The lock is released when this is dropped, or when the process exits.
+///
+/// Only available on Unix.
+///
+/// Lock files are kept in `/run/lock`, which is shared by every user. Where
+/// there is no `/run/lock`, or the user can't create files in it, they go in
+/// `$XDG_RUNTIME_DIR` instead, and failing that the temporary directory. Only
+/// processes which end up in the same directory keep each other out, which
+/// for `$XDG_RUNTIME_DIR` means processes of the same user.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::X52MFDLine;
+/// use std::time::Duration;
+///
+/// let driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// let _lock = driver.lock_timeout(Duration::from_secs(1)).unwrap();
+/// driver.set_mfd_text(&X52MFDLine::Line1, "Mine".to_string()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct X52Lock {
+	file: File,
+	id: X52DeviceId,
+}
+
+impl X52Lock {
+	/// Takes the lock of the X52 with the given ID, waiting for as long as
+	/// another process holds it
+	pub fn acquire(id: &X52DeviceId) -> Result<Self, Error> {
+		let file = open_lock(id)?;
+		flock(&file, libc::LOCK_EX)?;
+		Ok(Self {
+			file,
+			id: id.clone(),
+		})
+	}
+
+	/// Takes the lock of the X52 with the given ID if nobody else holds it
+	///
+	/// Fails with [`ErrorId::DeviceLocked`] if another process holds it.
+	pub fn try_acquire(id: &X52DeviceId) -> Result<Self, Error> {
+		let file = open_lock(id)?;
+		match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+			Ok(()) => Ok(Self {
+				file,
+				id: id.clone(),
+			}),
+			Err(err) if err.kind() == ErrorKind::WouldBlock => Err(Error::new(
+				ErrorId::DeviceLocked,
+				format!("The X52 {id} is locked by another program"),
+			)),
+			Err(err) => Err(err.into()),
+		}
+	}
+
+	/// Takes the lock of the X52 with the given ID, waiting up to `timeout`
+	/// for another process to release it
+	///
+	/// Fails with [`ErrorId::DeviceLocked`] if the lock is still held after
+	/// `timeout`.
+	pub fn acquire_timeout(
+		id: &X52DeviceId,
+		timeout: Duration,
+	) -> Result<Self, Error> {
+		let deadline = Instant::now() + timeout;
+		loop {
+			match Self::try_acquire(id) {
+				Err(err)
+					if err.id() == Some(ErrorId::DeviceLocked)
+						&& Instant::now() < deadline =>
+				{
+					thread::sleep(LOCK_RETRY_INTERVAL);
+				}
+				result => return result,
+			}
+		}
+	}
+
+	/// The ID of the locked X52
+	pub fn id(&self) -> &X52DeviceId {
+		&self.id
+	}
+}
+
+impl Drop for X52Lock {
+	fn drop(&mut self) {
+		// Closing the file releases the lock anyways
+		let _ = flock(&self.file, libc::LOCK_UN);
+	}
+}
+
+impl X52Driver<UsbTransport> {
+	/// Takes the cross-process lock of this X52, see [`X52Lock::acquire`]
+	pub fn lock(&self) -> Result<X52Lock, Error> {
+		X52Lock::acquire(&self.id())
+	}
+
+	/// Takes the cross-process lock of this X52 if it is free, see
+	/// [`X52Lock::try_acquire`]
+	pub fn try_lock(&self) -> Result<X52Lock, Error> {
+		X52Lock::try_acquire(&self.id())
+	}
+
+	/// Takes the cross-process lock of this X52, waiting up to `timeout`, see
+	/// [`X52Lock::acquire_timeout`]
+	pub fn lock_timeout(&self, timeout: Duration) -> Result<X52Lock, Error> {
+		X52Lock::acquire_timeout(&self.id(), timeout)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lock_file_name_ignores_serial() {
+		assert_eq!(
+			lock_file_name(&X52DeviceId::new(1, vec![2, 3], Some("A".into()))),
+			lock_file_name(&X52DeviceId::new(1, vec![2, 3], None))
+		);
+	}
+
+	#[test]
+	fn unusable_lock_dirs_are_skipped() {
+		let name = format!("rx52-test-{}.lock", std::process::id());
+		let temp = std::env::temp_dir();
+		let dirs = [PathBuf::from("/nonexistent/rx52"), temp.clone()];
+		open_lock_in(&dirs, &name).unwrap();
+		assert!(temp.join(&name).exists());
+		let _ = std::fs::remove_file(temp.join(&name));
+	}
+
+	#[test]
+	fn second_lock_fails_until_released() {
+		// A bus number no real X52 is on, so this can't collide with one
+		let id = X52DeviceId::new(255, vec![std::process::id() as u8], None);
+		let lock = X52Lock::try_acquire(&id).unwrap();
+		let err = X52Lock::acquire_timeout(&id, Duration::from_millis(50))
+			.unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::DeviceLocked));
+		drop(lock);
+		X52Lock::try_acquire(&id).unwrap();
+		for dir in lock_dirs() {
+			let _ = std::fs::remove_file(dir.join(lock_file_name(&id)));
+		}
+	}
+}
@@ -0,0 +1,83 @@
+//! Logging every vendor request sent to an X52, with the `log` feature
+
+use crate::{Error, X52VendorCommand};
+use std::fmt::Arguments;
+use std::time::Duration;
+
+/// The target every message is logged under
+const TARGET: &str = "rx52";
+
+/// Logs a high level call and the requests it is made of, like
+/// `set LED A to Red -> 0xB8 0x0201, 0xB8 0x0300`
+pub(crate) fn log_operation(operation: Arguments<'_>, requests: &[(u16, u16)]) {
+	if !log::log_enabled!(target: TARGET, log::Level::Debug) {
+		return;
+	}
+	let requests = requests
+		.iter()
+		.map(|(index, value)| format!("{index:#04X} {value:#06X}"))
+		.collect::<Vec<_>>()
+		.join(", ");
+	log::debug!(target: TARGET, "{operation} -> {requests}");
+}
+
+/// Describes a request, like `LED A red on (0xB8 0x0201)`
+///
+/// Requests which aren't a known [`X52VendorCommand`] are described by the
+/// name of their command, if any, and their value.
+fn describe_request(index: u16, value: u16) -> String {
+	match X52VendorCommand::decode(index, value) {
+		Some(command) => format!("{command} ({index:#04X} {value:#06X})"),
+		None => {
+			let name = X52VendorCommand::command_name(index)
+				.unwrap_or("UNKNOWN_COMMAND");
+			format!("{name} ({index:#04X}) value {value:#06X}")
+		}
+	}
+}
+
+/// Logs a single request once it is done
+pub(crate) fn log_request(
+	index: u16,
+	value: u16,
+	latency: Duration,
+	result: &Result<(), Error>,
+) {
+	match result {
+		Ok(()) => log::trace!(
+			target: TARGET,
+			"{} took {latency:?}",
+			describe_request(index, value)
+		),
+		Err(err) => log::warn!(
+			target: TARGET,
+			"{} failed after {latency:?}: {err}",
+			describe_request(index, value)
+		),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn requests_are_decoded() {
+		assert_eq!(
+			describe_request(0xB8, 0x0201),
+			"LED A red on (0xB8 0x0201)"
+		);
+		assert_eq!(
+			describe_request(0xB8, 0x0200),
+			"LED A red off (0xB8 0x0200)"
+		);
+		assert_eq!(
+			describe_request(0xFD, 0x1234),
+			"SET_SHIFT_STATUS_COMMAND (0xFD) value 0x1234"
+		);
+		assert_eq!(
+			describe_request(0x00, 1),
+			"UNKNOWN_COMMAND (0x00) value 0x0001"
+		);
+	}
+}
@@ -0,0 +1,318 @@
+//! Managing several X52s at once
+
+use crate::device::get_devices_in;
+use crate::{
+	Error, ErrorId, X52DeviceId, X52Driver, X52HotplugEvent, X52HotplugWatcher,
+};
+use rusb::Context;
+use std::collections::HashMap;
+
+/// Which of the managed X52s a command is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X52Target<'a> {
+	/// The X52 with the given ID
+	Id(&'a X52DeviceId),
+	/// The X52 given this name with [`X52Manager::set_name`]
+	Name(&'a str),
+	/// Every X52 in the group with this name
+	Group(&'a str),
+	/// Every attached X52
+	All,
+}
+
+/// The result of a command for each X52 it was sent to by
+/// [`X52Manager::send`]
+pub type X52TargetResults = Vec<(X52DeviceId, Result<(), Error>)>;
+
+/// An X52 opened by the manager
+struct ManagedDevice {
+	id: X52DeviceId,
+	driver: X52Driver,
+}
+
+/// Keeps track of every attached X52, sharing one USB context between them
+///
+/// X52s can be given names and put into groups, both of which refer to the
+/// stable [`X52DeviceId`] of the device, so they can be set up before the
+/// device is plugged in and survive it being replugged.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::{X52ColoredLed, X52ColoredLedStatus, X52Manager, X52Target};
+///
+/// let mut manager = X52Manager::new().unwrap();
+/// manager.set_name("left", "1-2".parse().unwrap());
+/// manager.set_name("right", "1-3".parse().unwrap());
+/// manager.add_to_group("sticks", "1-2".parse().unwrap());
+/// manager.add_to_group("sticks", "1-3".parse().unwrap());
+///
+/// // Mirror LED A across both sticks
+/// for (id, result) in manager
+///     .send(X52Target::Group("sticks"), |x| {
+///         x.toggle_led_colored(&X52ColoredLed::A, &X52ColoredLedStatus::Red)
+///     })
+///     .unwrap()
+/// {
+///     if let Err(err) = result {
+///         eprintln!("{id}: {err}");
+///     }
+/// }
+/// ```
+pub struct X52Manager {
+	context: Context,
+	devices: Vec<ManagedDevice>,
+	names: HashMap<String, X52DeviceId>,
+	groups: HashMap<String, Vec<X52DeviceId>>,
+}
+
+impl X52Manager {
+	/// Creates a manager and opens every attached X52
+	///
+	/// X52s which can't be opened are skipped, see
+	/// [`X52Manager::refresh`] to find out why.
+	pub fn new() -> Result<Self, Error> {
+		let mut manager = Self {
+			context: Context::new()?,
+			devices: Vec::new(),
+			names: HashMap::new(),
+			groups: HashMap::new(),
+		};
+		let _ = manager.refresh();
+		Ok(manager)
+	}
+
+	/// The USB context shared by every managed X52
+	pub fn context(&self) -> &Context {
+		&self.context
+	}
+
+	/// Opens any newly attached X52s and forgets any which are gone
+	///
+	/// Every X52 is tried even if opening one fails, in which case the first
+	/// error is returned.
+	pub fn refresh(&mut self) -> Result<(), Error> {
+		let entries = get_devices_in(&self.context)?;
+		self.devices.retain(|x| {
+			entries
+				.iter()
+				.any(|y| (y.bus(), y.address()) == x.driver.get_bus_device())
+		});
+		let mut result = Ok(());
+		for entry in entries {
+			let bus_device = (entry.bus(), entry.address());
+			if self
+				.devices
+				.iter()
+				.any(|x| x.driver.get_bus_device() == bus_device)
+			{
+				continue;
+			}
+			match entry.open() {
+				Ok(driver) => self.devices.push(ManagedDevice {
+					id: entry.id(),
+					driver,
+				}),
+				Err(err) => {
+					if result.is_ok() {
+						result = Err(err);
+					}
+				}
+			}
+		}
+		result
+	}
+
+	/// Watches for X52s being plugged in and unplugged
+	///
+	/// Pass the events to [`X52Manager::handle_event`] to keep the manager up
+	/// to date.
+	pub fn watch(&self) -> X52HotplugWatcher {
+		X52HotplugWatcher::new_in(self.context.clone())
+	}
+
+	/// Updates the managed X52s after a hotplug event
+	pub fn handle_event(
+		&mut self,
+		event: &X52HotplugEvent,
+	) -> Result<(), Error> {
+		match event {
+			X52HotplugEvent::Connected { .. } => self.refresh(),
+			X52HotplugEvent::Disconnected { bus_device, .. } => {
+				self.devices
+					.retain(|x| x.driver.get_bus_device() != *bus_device);
+				Ok(())
+			}
+		}
+	}
+
+	/// The IDs of every managed X52
+	pub fn ids(&self) -> impl Iterator<Item = &X52DeviceId> {
+		self.devices.iter().map(|x| &x.id)
+	}
+
+	/// Gets the driver of a single managed X52 by its ID or name
+	///
+	/// Returns [`None`] for [`X52Target::Group`] and [`X52Target::All`].
+	pub fn get(&self, target: X52Target<'_>) -> Option<&X52Driver> {
+		let id = match target {
+			X52Target::Id(id) => id,
+			X52Target::Name(name) => self.names.get(name)?,
+			X52Target::Group(_) | X52Target::All => return None,
+		};
+		self.devices
+			.iter()
+			.find(|x| refers_to(id, &x.id))
+			.map(|x| &x.driver)
+	}
+
+	/// Gives the X52 with the given ID a name, replacing any previous one
+	pub fn set_name(&mut self, name: &str, id: X52DeviceId) {
+		self.names.insert(name.to_string(), id);
+	}
+
+	/// Removes a name given with [`X52Manager::set_name`]
+	pub fn remove_name(&mut self, name: &str) {
+		self.names.remove(name);
+	}
+
+	/// The name given to the X52 with the given ID, if any
+	pub fn name_of(&self, id: &X52DeviceId) -> Option<&str> {
+		self.names
+			.iter()
+			.find(|(_, x)| refers_to(x, id))
+			.map(|(name, _)| name.as_str())
+	}
+
+	/// Adds the X52 with the given ID to a group, creating it if needed
+	pub fn add_to_group(&mut self, group: &str, id: X52DeviceId) {
+		let members = self.groups.entry(group.to_string()).or_default();
+		if !members.contains(&id) {
+			members.push(id);
+		}
+	}
+
+	/// Removes the X52 with the given ID from a group
+	pub fn remove_from_group(&mut self, group: &str, id: &X52DeviceId) {
+		if let Some(members) = self.groups.get_mut(group) {
+			members.retain(|x| x != id);
+		}
+	}
+
+	/// Runs `command` on every attached X52 matching `target`
+	///
+	/// Returns the result of the command for each X52 it was run on. Fails
+	/// if `target` names a device or group which was never set up. Named
+	/// X52s which are not plugged in are skipped.
+	pub fn send<F>(
+		&self,
+		target: X52Target<'_>,
+		command: F,
+	) -> Result<X52TargetResults, Error>
+	where
+		F: Fn(&X52Driver) -> Result<(), Error>,
+	{
+		let ids = resolve_target(&self.names, &self.groups, target)?;
+		Ok(self
+			.devices
+			.iter()
+			.filter(|x| {
+				ids.as_ref()
+					.is_none_or(|ids| ids.iter().any(|y| refers_to(y, &x.id)))
+			})
+			.map(|x| (x.id.clone(), command(&x.driver)))
+			.collect())
+	}
+}
+
+/// Whether `target`, as set up by the user, refers to the attached X52 `id`
+///
+/// Targets are often written without the serial number the attached X52
+/// reports, so this goes through [`X52DeviceId::matches`] rather than
+/// comparing the IDs.
+fn refers_to(target: &X52DeviceId, id: &X52DeviceId) -> bool {
+	target.matches(id.bus(), id.port_path(), id.serial())
+}
+
+/// Turns a target into the IDs it refers to, or [`None`] for every device
+fn resolve_target(
+	names: &HashMap<String, X52DeviceId>,
+	groups: &HashMap<String, Vec<X52DeviceId>>,
+	target: X52Target<'_>,
+) -> Result<Option<Vec<X52DeviceId>>, Error> {
+	match target {
+		X52Target::Id(id) => Ok(Some(vec![id.clone()])),
+		X52Target::Name(name) => match names.get(name) {
+			Some(id) => Ok(Some(vec![id.clone()])),
+			None => Err(Error::new(
+				ErrorId::UnknownTarget,
+				format!("No X52 is named \"{name}\""),
+			)),
+		},
+		X52Target::Group(group) => match groups.get(group) {
+			Some(ids) => Ok(Some(ids.clone())),
+			None => Err(Error::new(
+				ErrorId::UnknownTarget,
+				format!("There is no group of X52s named \"{group}\""),
+			)),
+		},
+		X52Target::All => Ok(None),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolve_names_and_groups() {
+		let left = X52DeviceId::new(1, vec![2], None);
+		let right = X52DeviceId::new(1, vec![3], None);
+		let names = HashMap::from([("left".to_string(), left.clone())]);
+		let groups = HashMap::from([(
+			"sticks".to_string(),
+			vec![left.clone(), right.clone()],
+		)]);
+
+		assert_eq!(
+			resolve_target(&names, &groups, X52Target::Name("left")).unwrap(),
+			Some(vec![left.clone()])
+		);
+		assert_eq!(
+			resolve_target(&names, &groups, X52Target::Group("sticks"))
+				.unwrap(),
+			Some(vec![left, right.clone()])
+		);
+		assert_eq!(
+			resolve_target(&names, &groups, X52Target::Id(&right)).unwrap(),
+			Some(vec![right])
+		);
+		assert_eq!(
+			resolve_target(&names, &groups, X52Target::All).unwrap(),
+			None
+		);
+	}
+
+	#[test]
+	fn targets_match_ids_with_serials() {
+		let attached = X52DeviceId::new(1, vec![2], Some("ABC".to_string()));
+		let moved = X52DeviceId::new(1, vec![3], Some("ABC".to_string()));
+		for target in ["1-2", "1-3#ABC", "1-2#ABC"] {
+			let target: X52DeviceId = target.parse().unwrap();
+			assert!(refers_to(&target, &attached), "{target}");
+		}
+		assert!(refers_to(&attached, &moved));
+		assert!(!refers_to(&"1-3".parse().unwrap(), &attached));
+		assert!(!refers_to(&"1-2#DEF".parse().unwrap(), &attached));
+	}
+
+	#[test]
+	fn resolve_unknown() {
+		let names = HashMap::new();
+		let groups = HashMap::new();
+		for target in [X52Target::Name("left"), X52Target::Group("sticks")] {
+			let err = resolve_target(&names, &groups, target).unwrap_err();
+			assert_eq!(err.id(), Some(ErrorId::UnknownTarget));
+		}
+	}
+}
@@ -0,0 +1,440 @@
+//! Turning high level operations into vendor requests and back, without any
+//! I/O
+//!
+//! This is the encoding [`X52Driver`](crate::X52Driver) uses, as plain
+//! functions which never touch USB. Use it to drive an X52 from another
+//! runtime, to build a transport which understands operations rather than
+//! single requests, or to check what a capture of USB traffic did.
+//!
+//! # Examples
+//!
+//! ```
+//! use rx52::protocol;
+//! use rx52::{X52Command, X52DateFormat};
+//!
+//! let command = X52Command::SetDate {
+//!     day: 31,
+//!     month: 12,
+//!     year: 24,
+//!     format: X52DateFormat::DDMMYY,
+//! };
+//! let requests = protocol::encode_requests(&command).unwrap();
+//! assert_eq!(requests, vec![(0xC4, 0x0C1F), (0xC8, 24)]);
+//! assert_eq!(
+//!     protocol::decode(&requests, X52DateFormat::DDMMYY).unwrap(),
+//!     vec![command]
+//! );
+//! ```
+
+use crate::{
+	Error, ErrorId, X52Clocks, X52ColoredLed, X52ColoredLedStatus, X52Command,
+	X52DateFormat, X52OnOffLed, X52OnOffLedStatus, X52VendorCommand,
+};
+use rx52_core::{X52CoreError, MFD_LINE_SIZE};
+
+/// Checks that `text` can be shown on a line of the MFD
+pub(crate) fn check_mfd_text(text: &str) -> Result<(), Error> {
+	match rx52_core::check_mfd_text(text.as_bytes()) {
+		Err(X52CoreError::MFDNotASCII) => Err(Error::new(
+			ErrorId::MFDNotASCII,
+			format!("The text \"{text}\" contains non-ASCII characters"),
+		)),
+		Err(_) => Err(Error::new(
+			ErrorId::MFDLineTooLong,
+			format!("The text \"{text}\" is too long to fit on the MFD"),
+		)),
+		Ok(()) => Ok(()),
+	}
+}
+
+/// Checks that `offset` is a valid offset from clock 1 for `clock`
+pub(crate) fn check_clock_offset(
+	clock: &X52Clocks,
+	offset: i16,
+) -> Result<(), Error> {
+	rx52_core::check_clock_offset(offset).map_err(|_| {
+		Error::new(
+			ErrorId::ClockOffsetTooBig,
+			format!("Clock {} offset ({offset}) too large", *clock as u8 + 1),
+		)
+	})
+}
+
+/// Turns a high level command into the vendor requests making it up, in the
+/// order they have to be sent
+///
+/// Fails with the same errors as the matching [`X52Driver`](crate::X52Driver)
+/// method for arguments which don't make sense. Setting LEDs only works on an
+/// X52 Pro, which is up to the caller to check.
+pub fn encode(command: &X52Command) -> Result<Vec<X52VendorCommand>, Error> {
+	command.validate()?;
+	Ok(match command {
+		X52Command::SetColoredLed(led, status) => {
+			rx52_core::encode_colored_led(*led, *status).to_vec()
+		}
+		X52Command::SetOnOffLed(led, status) => {
+			vec![rx52_core::encode_on_off_led(*led, *status)]
+		}
+		X52Command::SetMfdText(line, text) => {
+			rx52_core::encode_mfd_text(*line, text.as_bytes())
+				.expect("validated above")
+				.to_vec()
+		}
+		X52Command::ClearMfdLine(line) => {
+			vec![X52VendorCommand::ClearMfdLine(*line)]
+		}
+		X52Command::SetLedBrightness(x) => {
+			vec![X52VendorCommand::SetLedBrightness(*x)]
+		}
+		X52Command::SetMfdBrightness(x) => {
+			vec![X52VendorCommand::SetMfdBrightness(*x)]
+		}
+		X52Command::SetShiftStatus(x) => {
+			vec![X52VendorCommand::SetShiftStatus(*x)]
+		}
+		X52Command::SetBlinkStatus(x) => {
+			vec![X52VendorCommand::SetBlinkStatus(*x)]
+		}
+		X52Command::SetClock1 {
+			hour,
+			minute,
+			use_24h,
+		} => vec![X52VendorCommand::SetClock1 {
+			hour: *hour,
+			minute: *minute,
+			use_24h: *use_24h,
+		}],
+		X52Command::SetClock2Offset { offset, use_24h } => {
+			vec![X52VendorCommand::SetClock2Offset {
+				offset: *offset,
+				use_24h: *use_24h,
+			}]
+		}
+		X52Command::SetClock3Offset { offset, use_24h } => {
+			vec![X52VendorCommand::SetClock3Offset {
+				offset: *offset,
+				use_24h: *use_24h,
+			}]
+		}
+		X52Command::SetDate {
+			day,
+			month,
+			year,
+			format,
+		} => rx52_core::encode_date(*day, *month, *year, *format).to_vec(),
+	})
+}
+
+/// Turns a high level command into the (index, value) pairs of the vendor
+/// requests making it up, see [`encode`]
+pub fn encode_requests(command: &X52Command) -> Result<Vec<(u16, u16)>, Error> {
+	Ok(encode(command)?.iter().map(|x| x.encode()).collect())
+}
+
+/// Strips the spaces an MFD line was padded with to center `text` on it
+///
+/// Only the shortest text which centers to the whole line is kept, so spaces
+/// the encoder couldn't have added are left alone.
+fn strip_centering(line: &[u8]) -> &[u8] {
+	let is_padding = |x: &[u8]| x.iter().all(|x| *x == b' ');
+	(0..=line.len())
+		.map(|len| ((line.len() - len) / 2, len))
+		.find(|&(left, len)| {
+			is_padding(&line[..left]) && is_padding(&line[left + len..])
+		})
+		.map_or(line, |(left, len)| &line[left..left + len])
+}
+
+/// Takes the next whole command off the front of `commands`, returning it
+/// with how many vendor commands it was made of
+fn decode_next(
+	commands: &[X52VendorCommand],
+	date_format: X52DateFormat,
+) -> Option<(X52Command, usize)> {
+	use X52VendorCommand as V;
+	Some(match *commands {
+		[V::SetLed { id, on }, ..]
+			if X52OnOffLed::from_led_id(id).is_some() =>
+		{
+			let led = X52OnOffLed::from_led_id(id)?;
+			let status = X52OnOffLedStatus::from_on(on);
+			(X52Command::SetOnOffLed(led, status), 1)
+		}
+		[V::SetLed {
+			id: red,
+			on: red_on,
+		}, V::SetLed {
+			id: green,
+			on: green_on,
+		}, ..] => {
+			let led = X52ColoredLed::from_led_ids(red, green)?;
+			let status = X52ColoredLedStatus::from_led_states(red_on, green_on);
+			(X52Command::SetColoredLed(led, status), 2)
+		}
+		[V::ClearMfdLine(line), ref rest @ ..] => {
+			let mut text = Vec::new();
+			for command in rest.iter().take(MFD_LINE_SIZE / 2) {
+				match *command {
+					V::WriteMfdLine { line: x, chars } if x == line => {
+						text.extend_from_slice(&chars)
+					}
+					_ => break,
+				}
+			}
+			if text.is_empty() {
+				(X52Command::ClearMfdLine(line), 1)
+			} else {
+				let used = 1 + text.len() / 2;
+				let text =
+					String::from_utf8(strip_centering(&text).to_vec()).ok()?;
+				(X52Command::SetMfdText(line, text), used)
+			}
+		}
+		[V::SetLedBrightness(x), ..] => (X52Command::SetLedBrightness(x), 1),
+		[V::SetMfdBrightness(x), ..] => (X52Command::SetMfdBrightness(x), 1),
+		[V::SetShiftStatus(x), ..] => (X52Command::SetShiftStatus(x), 1),
+		[V::SetBlinkStatus(x), ..] => (X52Command::SetBlinkStatus(x), 1),
+		[V::SetClock1 {
+			hour,
+			minute,
+			use_24h,
+		}, ..] => (
+			X52Command::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			},
+			1,
+		),
+		[V::SetClock2Offset { offset, use_24h }, ..] => {
+			(X52Command::SetClock2Offset { offset, use_24h }, 1)
+		}
+		[V::SetClock3Offset { offset, use_24h }, ..] => {
+			(X52Command::SetClock3Offset { offset, use_24h }, 1)
+		}
+		[V::SetDateFields { first, second }, V::SetDateLastField(last), ..] => {
+			let (day, month, year) =
+				date_format.from_fields((first, second, last));
+			(
+				X52Command::SetDate {
+					day,
+					month,
+					year,
+					format: date_format,
+				},
+				2,
+			)
+		}
+		_ => return None,
+	})
+}
+
+/// Turns vendor requests back into the high level commands they were encoded
+/// from, see [`encode`]
+///
+/// The X52 is only told the fields of the date from left to right, so which
+/// is the day, month and year is taken from `date_format`. MFD text is
+/// given without the padding used to center it, keeping any other spaces.
+/// Text starting with spaces can center exactly like shorter text though,
+/// so `" AB"` and `" AB "` both come back as `"AB"`.
+///
+/// Fails with [`ErrorId::UndecodableRequests`] if a request is not in the
+/// [`X52VendorCommand`] catalogue, or is only part of a command, like the red
+/// half of a colored LED.
+pub fn decode(
+	requests: &[(u16, u16)],
+	date_format: X52DateFormat,
+) -> Result<Vec<X52Command>, Error> {
+	let commands = requests
+		.iter()
+		.map(|&(index, value)| {
+			X52VendorCommand::decode(index, value).ok_or_else(|| {
+				Error::new(
+					ErrorId::UndecodableRequests,
+					format!(
+						"The request {index:#04X} {value:#06X} is not a known \
+						 command"
+					),
+				)
+			})
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let mut decoded = Vec::new();
+	let mut at = 0;
+	while at < commands.len() {
+		let (command, used) = decode_next(&commands[at..], date_format)
+			.ok_or_else(|| {
+				Error::new(
+					ErrorId::UndecodableRequests,
+					format!(
+						"Request {} ({}) is not the start of a whole command",
+						at + 1,
+						commands[at]
+					),
+				)
+			})?;
+		decoded.push(command);
+		at += used;
+	}
+	Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::X52MFDLine;
+
+	fn round_trip(command: X52Command, date_format: X52DateFormat) {
+		let requests = encode_requests(&command).unwrap();
+		assert_eq!(decode(&requests, date_format).unwrap(), vec![command]);
+	}
+
+	#[test]
+	fn every_led_round_trips() {
+		let colors = [
+			X52ColoredLedStatus::Off,
+			X52ColoredLedStatus::Red,
+			X52ColoredLedStatus::Green,
+			X52ColoredLedStatus::Amber,
+		];
+		for led in X52ColoredLed::ALL {
+			for status in colors {
+				round_trip(
+					X52Command::SetColoredLed(led, status),
+					X52DateFormat::DDMMYY,
+				);
+			}
+		}
+		for led in X52OnOffLed::ALL {
+			for status in [X52OnOffLedStatus::Off, X52OnOffLedStatus::On] {
+				round_trip(
+					X52Command::SetOnOffLed(led, status),
+					X52DateFormat::DDMMYY,
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn every_value_round_trips() {
+		let format = X52DateFormat::DDMMYY;
+		for x in 0..=u8::MAX {
+			round_trip(X52Command::SetLedBrightness(x), format);
+			round_trip(X52Command::SetMfdBrightness(x), format);
+		}
+		for x in [false, true] {
+			round_trip(X52Command::SetShiftStatus(x), format);
+			round_trip(X52Command::SetBlinkStatus(x), format);
+		}
+		for use_24h in [false, true] {
+			for hour in 0..24 {
+				for minute in 0..60 {
+					round_trip(
+						X52Command::SetClock1 {
+							hour,
+							minute,
+							use_24h,
+						},
+						format,
+					);
+				}
+			}
+			for offset in -1023..=1023 {
+				round_trip(
+					X52Command::SetClock2Offset { offset, use_24h },
+					format,
+				);
+				round_trip(
+					X52Command::SetClock3Offset { offset, use_24h },
+					format,
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn every_date_format_round_trips() {
+		for format in [
+			X52DateFormat::DDMMYY,
+			X52DateFormat::MMDDYY,
+			X52DateFormat::YYMMDD,
+		] {
+			round_trip(
+				X52Command::SetDate {
+					day: 31,
+					month: 12,
+					year: 24,
+					format,
+				},
+				format,
+			);
+		}
+	}
+
+	#[test]
+	fn mfd_text_round_trips() {
+		for line in X52MFDLine::ALL {
+			for text in [
+				"",
+				"A",
+				"Hello",
+				"exactly 16 chars",
+				"a  b",
+				"AB ",
+				"Hi   ",
+				"a  b ",
+			] {
+				round_trip(
+					X52Command::SetMfdText(line, text.to_string()),
+					X52DateFormat::DDMMYY,
+				);
+			}
+			round_trip(X52Command::ClearMfdLine(line), X52DateFormat::DDMMYY);
+		}
+	}
+
+	#[test]
+	fn only_centering_is_stripped() {
+		assert_eq!(strip_centering(b"       AB       "), b"AB");
+		assert_eq!(strip_centering(b"      AB        "), b"AB ");
+		assert_eq!(strip_centering(b"     A  B       "), b"A  B ");
+		assert_eq!(strip_centering(b"                "), b"");
+		assert_eq!(strip_centering(b"AB              "), b"AB             ");
+	}
+
+	#[test]
+	fn sequences_are_split_into_commands() {
+		let commands = vec![
+			X52Command::ClearMfdLine(X52MFDLine::Line1),
+			X52Command::SetMfdText(X52MFDLine::Line1, "Hi".to_string()),
+			X52Command::SetColoredLed(
+				X52ColoredLed::A,
+				X52ColoredLedStatus::Red,
+			),
+			X52Command::SetOnOffLed(X52OnOffLed::Fire, X52OnOffLedStatus::On),
+		];
+		let requests = commands
+			.iter()
+			.flat_map(|x| encode_requests(x).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(decode(&requests, X52DateFormat::DDMMYY).unwrap(), commands);
+	}
+
+	#[test]
+	fn partial_commands_are_rejected() {
+		let err = decode(&[(0xB8, 0x0201)], X52DateFormat::DDMMYY).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::UndecodableRequests));
+		let err = decode(&[(0x1234, 0)], X52DateFormat::DDMMYY).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::UndecodableRequests));
+	}
+
+	#[test]
+	fn invalid_commands_are_not_encoded() {
+		let err = encode(&X52Command::SetClock2Offset {
+			offset: 2000,
+			use_24h: true,
+		})
+		.unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::ClockOffsetTooBig));
+	}
+}
@@ -0,0 +1,204 @@
+//! Sending commands to an X52 from a background thread
+
+use crate::{Error, UsbTransport, X52Command, X52Driver, X52Transport};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A message to the worker thread of an [`X52CommandQueue`]
+enum Message {
+	Command(X52Command),
+	Flush(Sender<()>),
+}
+
+/// Adds `command` to `batch`, dropping any commands it supersedes
+fn coalesce(batch: &mut Vec<X52Command>, command: X52Command) {
+	batch.retain(|x| !command.supersedes(x));
+	batch.push(command);
+}
+
+/// The worker thread of an [`X52CommandQueue`]
+fn run_queue<T, F>(
+	driver: X52Driver<T>,
+	receiver: Receiver<Message>,
+	mut on_error: F,
+) -> X52Driver<T>
+where
+	T: X52Transport,
+	F: FnMut(X52Command, Error),
+{
+	// Blocks until there is something to do, and stops once every sender is
+	// gone
+	while let Ok(first) = receiver.recv() {
+		let mut batch = Vec::new();
+		let mut flushes = Vec::new();
+		// Collect everything that piled up while the last batch was sent
+		for message in std::iter::once(first).chain(receiver.try_iter()) {
+			match message {
+				Message::Command(command) => coalesce(&mut batch, command),
+				Message::Flush(sender) => flushes.push(sender),
+			}
+		}
+		for command in batch {
+			if let Err(err) = driver.execute(&command) {
+				on_error(command, err);
+			}
+		}
+		for sender in flushes {
+			let _ = sender.send(());
+		}
+	}
+	driver
+}
+
+/// Sends commands to an X52 from a background thread
+///
+/// The queue takes ownership of the driver and moves it to a worker thread.
+/// [`X52CommandQueue::send`] never waits for the X52, so it can be called from
+/// event loops which can't afford to block on USB.
+///
+/// Commands which pile up while the worker is busy are coalesced: if several
+/// commands change the same part of the X52 (see [`X52Command::supersedes`])
+/// only the last one is sent. Errors are passed to a callback on the worker
+/// thread, along with the command that failed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::{X52ColoredLed, X52ColoredLedStatus, X52Command, X52CommandQueue};
+///
+/// let driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// let queue = X52CommandQueue::new(driver, |command, err| {
+///     eprintln!("{command:?} failed: {err}")
+/// });
+/// queue.send(X52Command::SetColoredLed(
+///     X52ColoredLed::A,
+///     X52ColoredLedStatus::Green,
+/// ));
+/// ```
+pub struct X52CommandQueue<T: X52Transport + Send + 'static = UsbTransport> {
+	sender: Option<Sender<Message>>,
+	thread: Option<JoinHandle<X52Driver<T>>>,
+}
+
+impl<T: X52Transport + Send + 'static> X52CommandQueue<T> {
+	/// Starts a worker thread sending commands to `driver`
+	///
+	/// `on_error` is called on the worker thread whenever a command fails.
+	pub fn new<F>(driver: X52Driver<T>, on_error: F) -> Self
+	where
+		F: FnMut(X52Command, Error) + Send + 'static,
+	{
+		let (sender, receiver) = mpsc::channel();
+		Self {
+			sender: Some(sender),
+			thread: Some(thread::spawn(move || {
+				run_queue(driver, receiver, on_error)
+			})),
+		}
+	}
+
+	/// Queues a command without waiting for it to be sent
+	pub fn send(&self, command: X52Command) {
+		self.send_message(Message::Command(command));
+	}
+
+	/// Waits until every command queued so far has been sent
+	pub fn flush(&self) {
+		let (sender, receiver) = mpsc::channel();
+		self.send_message(Message::Flush(sender));
+		// Only fails if the worker is gone, in which case nothing is left to
+		// wait for
+		let _ = receiver.recv();
+	}
+
+	/// Sends every queued command, stops the worker and gives the driver back
+	pub fn into_driver(mut self) -> X52Driver<T> {
+		self.stop().expect("the worker thread is only joined once")
+	}
+
+	fn send_message(&self, message: Message) {
+		if let Some(sender) = &self.sender {
+			// The worker only goes away if the error callback panicked, which
+			// has already been reported by the panic itself
+			let _ = sender.send(message);
+		}
+	}
+
+	/// Stops the worker after it finishes what is queued
+	fn stop(&mut self) -> Option<X52Driver<T>> {
+		self.sender.take();
+		match self.thread.take()?.join() {
+			Ok(driver) => Some(driver),
+			Err(panic) => std::panic::resume_unwind(panic),
+		}
+	}
+}
+
+impl<T: X52Transport + Send + 'static> Drop for X52CommandQueue<T> {
+	fn drop(&mut self) {
+		if !thread::panicking() {
+			self.stop();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		ErrorId, MockTransport, X52ColoredLed, X52ColoredLedStatus,
+		X52DeviceType, X52MFDLine,
+	};
+	use std::sync::{Arc, Mutex};
+
+	#[test]
+	fn coalesce_keeps_last_per_slot() {
+		let mut batch = Vec::new();
+		for status in [X52ColoredLedStatus::Red, X52ColoredLedStatus::Green] {
+			coalesce(
+				&mut batch,
+				X52Command::SetColoredLed(X52ColoredLed::A, status),
+			);
+		}
+		coalesce(&mut batch, X52Command::SetLedBrightness(10));
+		coalesce(&mut batch, X52Command::ClearMfdLine(X52MFDLine::Line1));
+		coalesce(
+			&mut batch,
+			X52Command::SetMfdText(X52MFDLine::Line1, "Hi".to_string()),
+		);
+		assert_eq!(
+			batch,
+			vec![
+				X52Command::SetColoredLed(
+					X52ColoredLed::A,
+					X52ColoredLedStatus::Green
+				),
+				X52Command::SetLedBrightness(10),
+				X52Command::SetMfdText(X52MFDLine::Line1, "Hi".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn queue_sends_and_reports_errors() {
+		let errors = Arc::new(Mutex::new(Vec::new()));
+		let queue_errors = errors.clone();
+		let queue = X52CommandQueue::new(
+			X52Driver::new(MockTransport::new(), &X52DeviceType::X52),
+			move |command, err: Error| {
+				queue_errors.lock().unwrap().push((command, err.id()))
+			},
+		);
+		queue.send(X52Command::SetColoredLed(
+			X52ColoredLed::B,
+			X52ColoredLedStatus::Red,
+		));
+		queue.send(X52Command::SetMfdBrightness(42));
+		queue.flush();
+		assert_eq!(errors.lock().unwrap().len(), 1);
+		assert_eq!(errors.lock().unwrap()[0].1, Some(ErrorId::Unsupported));
+
+		let driver = queue.into_driver();
+		assert_eq!(driver.transport().requests(), vec![(0xB1, 42)]);
+	}
+}
@@ -0,0 +1,194 @@
+//! Registering X52s with USB IDs rx52 doesn't know about
+
+use crate::{
+	Error, ErrorId, X52Descriptor, X52DeviceType, POSSIBLE_DESCRIPTORS,
+};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::RwLock;
+
+/// The descriptors registered at runtime, newest first
+static REGISTERED: RwLock<Vec<&'static X52Descriptor>> =
+	RwLock::new(Vec::new());
+
+/// Every descriptor rx52 recognises, registered ones before built-in ones
+pub(crate) fn descriptors() -> Vec<&'static X52Descriptor> {
+	let registered = REGISTERED.read().unwrap_or_else(|x| x.into_inner());
+	registered
+		.iter()
+		.copied()
+		.chain(POSSIBLE_DESCRIPTORS.iter())
+		.collect()
+}
+
+/// Every descriptor rx52 recognises as an X52
+///
+/// This is the built-in table followed by anything added with
+/// [`register_descriptor`], with registered descriptors first.
+pub fn known_descriptors() -> Vec<&'static X52Descriptor> {
+	descriptors()
+}
+
+/// Recognises the device with the given vendor and product IDs as an X52
+///
+/// This is for X52 revisions which are newer than this version of rx52.
+/// Every function finding or opening X52s picks it up straight away.
+/// Registering IDs which are already known replaces what they map to,
+/// including the built-in descriptors.
+///
+/// <div class="warning">Registered descriptors last for the rest of the
+/// program, even once replaced, so register each one once rather than in a
+/// loop.</div>
+///
+/// # Examples
+///
+/// ```
+/// use rx52::X52DeviceType;
+///
+/// let descriptor = rx52::register_descriptor(
+///     0x06A3,
+///     0x1234,
+///     X52DeviceType::X52Pro,
+///     "Some future X52 Pro",
+/// );
+/// assert_eq!(descriptor.x52_type(), &X52DeviceType::X52Pro);
+/// ```
+pub fn register_descriptor(
+	vendor: u16,
+	product: u16,
+	x52_type: X52DeviceType,
+	description: &str,
+) -> &'static X52Descriptor {
+	let descriptor: &'static X52Descriptor =
+		Box::leak(Box::new(X52Descriptor {
+			x52_type: match x52_type {
+				X52DeviceType::X52 => &X52DeviceType::X52,
+				X52DeviceType::X52Pro => &X52DeviceType::X52Pro,
+			},
+			vendor: Box::leak(Box::new(vendor)),
+			product: Box::leak(Box::new(product)),
+			description: Box::leak(description.into()),
+		}));
+	let mut registered = REGISTERED.write().unwrap_or_else(|x| x.into_inner());
+	registered.retain(|x| !x.eq_ids(vendor, product));
+	registered.insert(0, descriptor);
+	descriptor
+}
+
+/// Parses a single line of a descriptor file
+fn parse_descriptor_line(
+	line: &str,
+) -> Option<(u16, u16, X52DeviceType, Option<&str>)> {
+	let mut parts = line.splitn(3, char::is_whitespace);
+	let (vendor, product) = parts.next()?.split_once(':')?;
+	let vendor = u16::from_str_radix(vendor, 16).ok()?;
+	let product = u16::from_str_radix(product, 16).ok()?;
+	let x52_type = match parts.next()? {
+		"X52" => X52DeviceType::X52,
+		"X52Pro" => X52DeviceType::X52Pro,
+		_ => return None,
+	};
+	let description = parts.next().map(str::trim).filter(|x| !x.is_empty());
+	Some((vendor, product, x52_type, description))
+}
+
+/// Registers every descriptor listed in `reader`, see [`register_descriptor`]
+///
+/// Each line is the vendor and product IDs in hex, the type of X52 (`X52` or
+/// `X52Pro`), and an optional description. Blank lines and lines starting
+/// with `#` are skipped.
+///
+/// ```text
+/// # vendor:product type description
+/// 06a3:1234 X52Pro Some future X52 Pro
+/// ```
+///
+/// Nothing is registered if any line is invalid, which fails with
+/// [`ErrorId::InvalidDescriptor`].
+pub fn register_descriptors<R: BufRead>(
+	reader: R,
+) -> Result<Vec<&'static X52Descriptor>, Error> {
+	let mut parsed = Vec::new();
+	for (number, line) in reader.lines().enumerate() {
+		let line = line?;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let (vendor, product, x52_type, description) =
+			parse_descriptor_line(line).ok_or_else(|| {
+				Error::new(
+					ErrorId::InvalidDescriptor,
+					format!(
+						"Line {} is not a vendor:product ID followed by X52 \
+						 or X52Pro",
+						number + 1
+					),
+				)
+			})?;
+		let description = description
+			.map(str::to_string)
+			.unwrap_or_else(|| x52_type.to_string());
+		parsed.push((vendor, product, x52_type, description));
+	}
+	Ok(parsed
+		.into_iter()
+		.map(|(vendor, product, x52_type, description)| {
+			register_descriptor(vendor, product, x52_type, &description)
+		})
+		.collect())
+}
+
+/// Registers every descriptor listed in the file at `path`, see
+/// [`register_descriptors`]
+pub fn register_descriptors_from_file(
+	path: impl AsRef<Path>,
+) -> Result<Vec<&'static X52Descriptor>, Error> {
+	register_descriptors(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::find_descriptor_by_ids;
+
+	#[test]
+	fn registered_descriptors_are_found() {
+		assert!(find_descriptor_by_ids(0xFFF0, 0x0001).is_none());
+		register_descriptor(0xFFF0, 0x0001, X52DeviceType::X52, "First");
+		let descriptor = register_descriptor(
+			0xFFF0,
+			0x0001,
+			X52DeviceType::X52Pro,
+			"Second",
+		);
+		let found = find_descriptor_by_ids(0xFFF0, 0x0001).unwrap();
+		assert!(std::ptr::eq(found, descriptor));
+		assert_eq!(descriptor.description(), "Second");
+		let count = known_descriptors()
+			.into_iter()
+			.filter(|x| x.eq_ids(0xFFF0, 0x0001))
+			.count();
+		assert_eq!(count, 1);
+	}
+
+	#[test]
+	fn descriptor_files_are_parsed() {
+		let file = "# A comment\n\n\
+			fff1:00aB X52Pro  Future X52 Pro \n\
+			FFF1:0002 X52\n";
+		let descriptors = register_descriptors(file.as_bytes()).unwrap();
+		assert_eq!(descriptors.len(), 2);
+		assert_eq!(descriptors[0].product(), &0x00AB);
+		assert_eq!(descriptors[0].description(), "Future X52 Pro");
+		assert_eq!(descriptors[1].x52_type(), &X52DeviceType::X52);
+		assert_eq!(descriptors[1].description(), "X52");
+
+		let file = "fff1:0003 X52\nfff1:0004 X53\n";
+		let err = register_descriptors(file.as_bytes()).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::InvalidDescriptor));
+		assert!(err.to_string().contains("Line 2"));
+		assert!(find_descriptor_by_ids(0xFFF1, 0x0003).is_none());
+	}
+}
@@ -0,0 +1,434 @@
+//! Keeping an X52 in the same state across resets and replugs
+
+use crate::command::CommandSlot;
+use crate::device::find_device_by_id;
+use crate::{
+	Error, ErrorId, UsbTransport, X52Command, X52DeviceId, X52Driver,
+	X52HotplugEvent, X52HotplugWatcher, X52State, X52Transport,
+};
+use rusb::Context;
+use std::time::Instant;
+
+/// Finds the X52 with the given ID and opens it again
+type Connector<T> =
+	Box<dyn FnMut(&X52DeviceId) -> Result<X52Driver<T>, Error> + Send>;
+
+/// Returns true if `err` means the driver lost its X52 and has to find it
+/// again
+///
+/// Other errors, like I/O errors, can be a hiccup of a device which is still
+/// there, so they are passed on rather than replaying everything.
+fn is_stale(err: &Error) -> bool {
+	err.id() == Some(ErrorId::DeviceDisconnected)
+}
+
+/// The minutes in a day
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// An [`X52Driver`] which survives its X52 being reset or replugged
+///
+/// An X52 which is power cycled, reset by a hub or enumerated again after
+/// suspend comes back blank, under a new bus address. A plain driver keeps
+/// talking to the old address and every call fails. This instead remembers
+/// the [`X52DeviceId`] of the X52 and everything sent to it. When a command
+/// fails because the X52 went away, the X52 is looked up again by its ID and
+/// everything sent so far is sent to it again, along with the command.
+///
+/// Clock 1 is sent again moved on by the time since it was set, so it still
+/// shows the right time after a suspend. The date is only sent again if
+/// clock 1 shows it hasn't changed since, and is left out otherwise.
+///
+/// Some resets don't make any command fail, for example if the X52 comes
+/// back before the next command is sent. Pass the events of
+/// [`X52ResilientDriver::watch`] to [`X52ResilientDriver::handle_event`] to
+/// catch those as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::{X52Command, X52MFDLine, X52ResilientDriver};
+///
+/// let driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// let mut driver = X52ResilientDriver::new(driver);
+/// let watcher = driver.watch();
+/// driver
+///     .execute(&X52Command::SetMfdText(X52MFDLine::Line1, "Hello".into()))
+///     .unwrap();
+///
+/// // Puts "Hello" back on the MFD whenever the X52 comes back
+/// for event in watcher.iter() {
+///     if let Err(err) = driver.handle_event(&event) {
+///         eprintln!("Could not restore the X52: {err}");
+///     }
+/// }
+/// ```
+pub struct X52ResilientDriver<T: X52Transport = UsbTransport> {
+	/// The USB context the X52 is looked up in, if it is on libusb
+	context: Option<Context>,
+	id: X52DeviceId,
+	driver: Option<X52Driver<T>>,
+	state: X52State,
+	/// The parts of the state which were set, and are sent again
+	replay: Vec<CommandSlot>,
+	/// When clock 1 was set
+	clock_1_set: Instant,
+	/// When the date was set
+	date_set: Instant,
+	connect: Connector<T>,
+}
+
+impl X52ResilientDriver<UsbTransport> {
+	/// Wraps a driver, assuming nothing has been sent to the X52 yet
+	///
+	/// Only what is sent through [`X52ResilientDriver::execute`] is sent
+	/// again when the X52 comes back.
+	pub fn new(driver: X52Driver) -> Self {
+		let mut resilient = Self::with_state(driver, X52State::default());
+		resilient.replay.clear();
+		resilient
+	}
+
+	/// Wraps a driver, assuming the X52 shows `state`
+	///
+	/// Every part of `state` which is set is sent again when the X52 comes
+	/// back, with clock 1 and the date as of now.
+	pub fn with_state(driver: X52Driver, state: X52State) -> Self {
+		let context = driver.transport().device().context().clone();
+		let id = driver.id();
+		let mut resilient =
+			Self::with_connector(driver, id, state, usb_connector(&context));
+		resilient.context = Some(context);
+		resilient
+	}
+
+	/// Finds the X52 with the given ID and sends `state` to it
+	///
+	/// Fails if the X52 is not attached.
+	pub fn open(id: X52DeviceId, state: X52State) -> Result<Self, Error> {
+		let context = Context::new()?;
+		let mut driver = Self {
+			connect: usb_connector(&context),
+			context: Some(context),
+			id,
+			driver: None,
+			state,
+			replay: CommandSlot::all().collect(),
+			clock_1_set: Instant::now(),
+			date_set: Instant::now(),
+		};
+		driver.reconnect()?;
+		Ok(driver)
+	}
+
+	/// Watches for the X52 being plugged in again
+	///
+	/// Pass the events to [`X52ResilientDriver::handle_event`].
+	pub fn watch(&self) -> X52HotplugWatcher {
+		let context = self.context.clone().or_else(|| {
+			let driver = self.driver.as_ref()?;
+			Some(driver.transport().device().context().clone())
+		});
+		match context {
+			Some(context) => X52HotplugWatcher::new_in(context),
+			None => X52HotplugWatcher::new_lazy(),
+		}
+	}
+}
+
+/// Finds X52s again through libusb
+fn usb_connector(context: &Context) -> Connector<UsbTransport> {
+	let context = context.clone();
+	Box::new(move |id| find_device_by_id(&context, id)?.open())
+}
+
+impl<T: X52Transport> X52ResilientDriver<T> {
+	/// Wraps a driver over any transport, assuming the X52 shows `state`
+	///
+	/// `connect` is called with `id` to find the X52 again whenever it went
+	/// away, and fails with [`ErrorId::DeviceIdNotFound`] if it isn't
+	/// attached. Every part of `state` which is set is sent again when the
+	/// X52 comes back, like with [`X52ResilientDriver::with_state`].
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use rx52::{
+	///     MockTransport, X52Command, X52DeviceId, X52DeviceType, X52Driver,
+	///     X52ResilientDriver, X52State,
+	/// };
+	///
+	/// let open = |_: &X52DeviceId| {
+	///     Ok(X52Driver::new(MockTransport::new(), &X52DeviceType::X52))
+	/// };
+	/// let id: X52DeviceId = "1-2".parse().unwrap();
+	/// let mut driver = X52ResilientDriver::with_connector(
+	///     open(&id).unwrap(),
+	///     id,
+	///     X52State::new(),
+	///     open,
+	/// );
+	/// driver.execute(&X52Command::SetShiftStatus(true)).unwrap();
+	/// ```
+	pub fn with_connector<F>(
+		driver: X52Driver<T>,
+		id: X52DeviceId,
+		state: X52State,
+		connect: F,
+	) -> Self
+	where
+		F: FnMut(&X52DeviceId) -> Result<X52Driver<T>, Error> + Send + 'static,
+	{
+		Self {
+			context: None,
+			id,
+			driver: Some(driver),
+			state,
+			replay: CommandSlot::all().collect(),
+			clock_1_set: Instant::now(),
+			date_set: Instant::now(),
+			connect: Box::new(connect),
+		}
+	}
+
+	/// The ID used to find the X52 again
+	pub fn id(&self) -> &X52DeviceId {
+		&self.id
+	}
+
+	/// Everything sent to the X52
+	pub fn state(&self) -> &X52State {
+		&self.state
+	}
+
+	/// The driver of the X52, if it is currently attached
+	pub fn driver(&self) -> Option<&X52Driver<T>> {
+		self.driver.as_ref()
+	}
+
+	/// Returns false if the X52 went away and has not been found since
+	pub fn is_connected(&self) -> bool {
+		self.driver.is_some()
+	}
+
+	/// Restores the X52 after a hotplug event, if the event is about it
+	///
+	/// Events about the X52 the driver is already attached to are ignored,
+	/// as it still shows the state.
+	pub fn handle_event(
+		&mut self,
+		event: &X52HotplugEvent,
+	) -> Result<(), Error> {
+		let id = event.id();
+		if !self.id.matches(id.bus(), id.port_path(), id.serial()) {
+			return Ok(());
+		}
+		match event {
+			X52HotplugEvent::Connected { bus_device, .. } => {
+				let attached = self.driver.as_ref().is_some_and(|x| {
+					x.transport().bus_device().is_none_or(|x| x == *bus_device)
+				});
+				if attached {
+					return Ok(());
+				}
+				self.reconnect()
+			}
+			X52HotplugEvent::Disconnected { .. } => {
+				self.driver = None;
+				Ok(())
+			}
+		}
+	}
+
+	/// Finds the X52 again and sends it everything sent so far
+	pub fn reconnect(&mut self) -> Result<(), Error> {
+		self.driver = None;
+		let driver = (self.connect)(&self.id)?;
+		for slot in CommandSlot::all().filter(|x| self.replay.contains(x)) {
+			let command = self.replay_command(slot);
+			if let Some(command) =
+				command.filter(|x| driver.supports(x.capability()))
+			{
+				driver.execute(&command)?;
+			}
+		}
+		self.driver = Some(driver);
+		Ok(())
+	}
+
+	/// The minutes since midnight of the day clock 1 was set, that clock 1
+	/// shows now
+	fn clock_1_now(&self) -> Option<i64> {
+		let (hour, minute) = self.state.clock_1()?;
+		let elapsed = self.clock_1_set.elapsed().as_secs() / 60;
+		Some(hour as i64 * 60 + minute as i64 + elapsed as i64)
+	}
+
+	/// The command sending the given part of the state again
+	fn replay_command(&self, slot: CommandSlot) -> Option<X52Command> {
+		let command = self.state.command_for(slot)?;
+		match command {
+			X52Command::SetClock1 { use_24h, .. } => {
+				let now = self.clock_1_now()?.rem_euclid(MINUTES_PER_DAY);
+				Some(X52Command::SetClock1 {
+					hour: (now / 60) as u8,
+					minute: (now % 60) as u8,
+					use_24h,
+				})
+			}
+			X52Command::SetDate { .. } => {
+				let now = self.clock_1_now()?;
+				let set = now - (self.date_set.elapsed().as_secs() / 60) as i64;
+				let same_day = now.div_euclid(MINUTES_PER_DAY)
+					== set.div_euclid(MINUTES_PER_DAY);
+				same_day.then_some(command)
+			}
+			_ => Some(command),
+		}
+	}
+
+	/// Runs a single [`X52Command`], finding the X52 again if it went away
+	///
+	/// The command is remembered even if the X52 can't be found, and is sent
+	/// once the X52 comes back. Invalid commands fail without being
+	/// remembered.
+	pub fn execute(&mut self, command: &X52Command) -> Result<(), Error> {
+		command.validate()?;
+		self.state.apply(command);
+		let slot = command.slot();
+		match slot {
+			CommandSlot::Clock1 => self.clock_1_set = Instant::now(),
+			CommandSlot::Date => self.date_set = Instant::now(),
+			_ => {}
+		}
+		if !self.replay.contains(&slot) {
+			self.replay.push(slot);
+		}
+		if let Some(driver) = &self.driver {
+			match driver.execute(command) {
+				Err(err) if is_stale(&err) => {}
+				result => return result,
+			}
+		}
+		// The state already holds the command, so reconnecting sends it
+		self.reconnect().map_err(|err| match err.id() {
+			Some(ErrorId::DeviceIdNotFound) => Error::new(
+				ErrorId::DeviceDisconnected,
+				format!("The X52 {} is not attached", self.id),
+			),
+			_ => err,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		find_descriptor_by_ids, MockTransport, X52DateFormat, X52DeviceType,
+	};
+	use rx52_core::SET_SHIFT_STATUS_COMMAND;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	#[test]
+	fn stale_errors() {
+		assert!(is_stale(&rusb::Error::NoDevice.into()));
+		assert!(!is_stale(&rusb::Error::Io.into()));
+		assert!(!is_stale(&rusb::Error::Timeout.into()));
+		assert!(!is_stale(&rusb::Error::Pipe.into()));
+		assert!(!is_stale(&Error::new(
+			ErrorId::MFDLineTooLong,
+			String::new()
+		)));
+	}
+
+	#[test]
+	fn state_is_replayed_after_reconnecting() {
+		let id = X52DeviceId::new(1, vec![2], None);
+		let transport = MockTransport::new();
+		transport.push_outcome(Err(rusb::Error::NoDevice));
+		let connects = Arc::new(AtomicUsize::new(0));
+		let counter = connects.clone();
+		let mut driver = X52ResilientDriver::with_connector(
+			X52Driver::new(transport, &X52DeviceType::X52),
+			id.clone(),
+			X52State::default(),
+			move |_| {
+				counter.fetch_add(1, Ordering::Relaxed);
+				Ok(X52Driver::new(MockTransport::new(), &X52DeviceType::X52))
+			},
+		);
+
+		driver.execute(&X52Command::SetShiftStatus(true)).unwrap();
+		assert_eq!(connects.load(Ordering::Relaxed), 1);
+		let requests = driver.driver().unwrap().transport().requests();
+		assert!(requests.contains(&(SET_SHIFT_STATUS_COMMAND, 0x51)));
+
+		let descriptor = find_descriptor_by_ids(0x06A3, 0x0255).unwrap();
+		let connected = X52HotplugEvent::Connected {
+			descriptor,
+			id: id.clone(),
+			bus_device: (1, 5),
+		};
+		driver.handle_event(&connected).unwrap();
+		assert_eq!(connects.load(Ordering::Relaxed), 1);
+
+		driver
+			.handle_event(&X52HotplugEvent::Disconnected {
+				descriptor,
+				id,
+				bus_device: (1, 5),
+			})
+			.unwrap();
+		assert!(!driver.is_connected());
+		driver.handle_event(&connected).unwrap();
+		assert_eq!(connects.load(Ordering::Relaxed), 2);
+		assert_eq!(driver.driver().unwrap().transport().requests(), requests);
+	}
+
+	#[test]
+	fn only_what_was_set_is_replayed() {
+		let id = X52DeviceId::new(1, vec![2], None);
+		let open = |_: &X52DeviceId| {
+			Ok(X52Driver::new(MockTransport::new(), &X52DeviceType::X52))
+		};
+		let mut driver = X52ResilientDriver::with_connector(
+			open(&id).unwrap(),
+			id.clone(),
+			X52State::new(),
+			open,
+		);
+		driver.replay.clear();
+		for command in [
+			X52Command::SetMfdBrightness(50),
+			X52Command::SetClock1 {
+				hour: 23,
+				minute: 30,
+				use_24h: true,
+			},
+			X52Command::SetDate {
+				day: 31,
+				month: 12,
+				year: 25,
+				format: X52DateFormat::DDMMYY,
+			},
+		] {
+			driver.execute(&command).unwrap();
+		}
+		// Suspended for 45 minutes, past midnight
+		let suspended = Duration::from_secs(45 * 60);
+		driver.clock_1_set = Instant::now().checked_sub(suspended).unwrap();
+		driver.date_set = driver.clock_1_set;
+		driver.reconnect().unwrap();
+
+		let expected =
+			X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		expected.set_mfd_brightness(50).unwrap();
+		expected.set_clock_1(0, 15, true).unwrap();
+		assert_eq!(
+			driver.driver().unwrap().transport().requests(),
+			expected.transport().requests()
+		);
+	}
+}
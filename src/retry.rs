@@ -0,0 +1,177 @@
+//! Timeouts and retries for the vendor requests sent by a driver
+
+use crate::transport::REQUEST_TIMEOUT;
+use crate::Error;
+use std::thread;
+use std::time::Duration;
+
+/// How a driver deals with vendor requests which fail or take too long
+///
+/// Set with [`X52Driver::set_retry_policy`](crate::X52Driver::set_retry_policy).
+/// The default waits 5 seconds for each request and never retries, which is
+/// how drivers have always behaved.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rx52::X52RetryPolicy;
+/// use std::time::Duration;
+///
+/// let mut driver = rx52::get_devices().unwrap()[0].open().unwrap();
+/// driver.set_retry_policy(X52RetryPolicy {
+///     timeout: Duration::from_millis(500),
+///     retries: 3,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X52RetryPolicy {
+	/// How long to wait for the X52 to accept a single request
+	pub timeout: Duration,
+	/// How many times to try a failed request again
+	pub retries: u32,
+	/// How long to wait before the first retry, doubled for every retry
+	/// after that
+	pub backoff: Duration,
+	/// The `rusb` errors worth retrying, anything else fails immediately
+	pub retry_on: Vec<rusb::Error>,
+}
+
+impl Default for X52RetryPolicy {
+	fn default() -> Self {
+		Self {
+			timeout: REQUEST_TIMEOUT,
+			retries: 0,
+			backoff: Duration::from_millis(50),
+			retry_on: vec![
+				rusb::Error::Timeout,
+				rusb::Error::Pipe,
+				rusb::Error::Io,
+				rusb::Error::Busy,
+				rusb::Error::Interrupted,
+			],
+		}
+	}
+}
+
+impl X52RetryPolicy {
+	/// Returns true if a request which failed with `err` should be retried
+	pub fn should_retry(&self, err: &Error) -> bool {
+		err.rusb_error().is_some_and(|x| self.retry_on.contains(&x))
+	}
+
+	/// How long to wait before the given retry, counting from 0
+	pub fn backoff_for(&self, retry: u32) -> Duration {
+		self.backoff.saturating_mul(1 << retry.min(16))
+	}
+
+	/// Runs `request` until it succeeds or the policy gives up
+	pub(crate) fn run<F>(&self, mut request: F) -> Result<(), Error>
+	where
+		F: FnMut() -> Result<(), Error>,
+	{
+		let mut retry = 0;
+		loop {
+			match request() {
+				Err(err) if retry < self.retries && self.should_retry(&err) => {
+					#[cfg(feature = "log")]
+					log::debug!(target: "rx52", "Retrying request: {err}");
+					thread::sleep(self.backoff_for(retry));
+					retry += 1;
+				}
+				result => return result,
+			}
+		}
+	}
+}
+
+/// Which vendor request of an operation failed
+///
+/// Most operations are a single request, but some take several, like
+/// setting a colored LED (one request each for red and green) or writing to
+/// the MFD. Every request before [`X52FailedStep::step`] went through, so the
+/// X52 may be left half way, for example with an LED showing only red.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct X52FailedStep {
+	pub(crate) step: usize,
+	pub(crate) steps: usize,
+	pub(crate) index: u16,
+	pub(crate) value: u16,
+}
+
+impl X52FailedStep {
+	/// The position of the failed request, counting from 0
+	pub fn step(&self) -> usize {
+		self.step
+	}
+
+	/// How many requests the operation is made of
+	pub fn steps(&self) -> usize {
+		self.steps
+	}
+
+	/// The index (command) of the failed request
+	pub fn index(&self) -> u16 {
+		self.index
+	}
+
+	/// The value of the failed request
+	pub fn value(&self) -> u16 {
+		self.value
+	}
+
+	/// Returns true if some of the operation already reached the X52
+	pub fn is_partial(&self) -> bool {
+		self.step > 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ErrorId;
+	use std::cell::Cell;
+
+	fn policy(retries: u32) -> X52RetryPolicy {
+		X52RetryPolicy {
+			retries,
+			backoff: Duration::ZERO,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn retries_until_success() {
+		let calls = Cell::new(0);
+		let result = policy(3).run(|| {
+			calls.set(calls.get() + 1);
+			match calls.get() {
+				3 => Ok(()),
+				_ => Err(rusb::Error::Timeout.into()),
+			}
+		});
+		assert!(result.is_ok());
+		assert_eq!(calls.get(), 3);
+	}
+
+	#[test]
+	fn gives_up_on_other_errors() {
+		let calls = Cell::new(0);
+		let result = policy(3).run(|| {
+			calls.set(calls.get() + 1);
+			Err(rusb::Error::NoDevice.into())
+		});
+		assert_eq!(result.unwrap_err().id(), Some(ErrorId::DeviceDisconnected));
+		assert_eq!(calls.get(), 1);
+	}
+
+	#[test]
+	fn backoff_doubles() {
+		let policy = X52RetryPolicy {
+			backoff: Duration::from_millis(10),
+			..Default::default()
+		};
+		assert_eq!(policy.backoff_for(0), Duration::from_millis(10));
+		assert_eq!(policy.backoff_for(2), Duration::from_millis(40));
+	}
+}
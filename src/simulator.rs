@@ -0,0 +1,453 @@
+//! A software X52, which acts on vendor requests like the real thing
+
+use crate::{
+	Error, X52ClockFormat, X52Clocks, X52ColoredLed, X52ColoredLedStatus,
+	X52DeviceType, X52Driver, X52MFDLine, X52OnOffLed, X52OnOffLedStatus,
+	X52Transport, X52VendorCommand,
+};
+use rx52_core::MFD_LINE_SIZE;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The number of LED IDs, including the unused ID 0
+const LED_IDS: usize = 21;
+
+/// Everything the simulated X52 keeps track of
+#[derive(Debug, Clone)]
+struct SimulatedDevice {
+	leds: [bool; LED_IDS],
+	mfd_text: [Vec<u8>; 3],
+	led_brightness: u8,
+	mfd_brightness: u8,
+	shift_status: bool,
+	blink_status: bool,
+	clock_1: (u8, u8),
+	clock_offsets: [i16; 2],
+	clock_24h: [bool; 3],
+	date_fields: (u8, u8, u8),
+}
+
+impl SimulatedDevice {
+	fn new() -> Self {
+		Self {
+			leds: [false; LED_IDS],
+			mfd_text: Default::default(),
+			led_brightness: 0,
+			mfd_brightness: 0,
+			shift_status: false,
+			blink_status: false,
+			clock_1: (0, 0),
+			clock_offsets: [0; 2],
+			clock_24h: [true; 3],
+			date_fields: (1, 1, 0),
+		}
+	}
+
+	/// Acts on a single command, like the firmware of an X52 would
+	fn handle(&mut self, command: X52VendorCommand) {
+		match command {
+			X52VendorCommand::SetLed { id, on } => {
+				if let Some(led) = self.leds.get_mut(id as usize) {
+					*led = on;
+				}
+			}
+			X52VendorCommand::SetLedBrightness(x) => self.led_brightness = x,
+			X52VendorCommand::SetMfdBrightness(x) => self.mfd_brightness = x,
+			X52VendorCommand::ClearMfdLine(line) => {
+				self.mfd_text[line as usize].clear()
+			}
+			// Characters are appended, and dropped once the line is full
+			X52VendorCommand::WriteMfdLine { line, chars } => {
+				let text = &mut self.mfd_text[line as usize];
+				for char in chars {
+					if text.len() < MFD_LINE_SIZE {
+						text.push(char);
+					}
+				}
+			}
+			X52VendorCommand::SetShiftStatus(x) => self.shift_status = x,
+			X52VendorCommand::SetBlinkStatus(x) => self.blink_status = x,
+			X52VendorCommand::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			} => {
+				self.clock_1 = (hour, minute);
+				self.clock_24h[0] = use_24h;
+			}
+			X52VendorCommand::SetClock2Offset { offset, use_24h } => {
+				self.clock_offsets[0] = offset;
+				self.clock_24h[1] = use_24h;
+			}
+			X52VendorCommand::SetClock3Offset { offset, use_24h } => {
+				self.clock_offsets[1] = offset;
+				self.clock_24h[2] = use_24h;
+			}
+			X52VendorCommand::SetDateFields { first, second } => {
+				self.date_fields.0 = first;
+				self.date_fields.1 = second;
+			}
+			X52VendorCommand::SetDateLastField(x) => self.date_fields.2 = x,
+		}
+	}
+}
+
+/// A software X52, which can be used anywhere a real one can
+///
+/// The simulator is a [`X52Transport`] which understands the same vendor
+/// requests as an X52, and remembers what they made it show: LED colors,
+/// MFD text, brightness, blinking, the shift indicator, clocks and the date.
+/// A simulated X52 (not Pro) ignores requests setting LEDs, since only the
+/// Pro can set them one by one.
+///
+/// Requests which are not in the [`X52VendorCommand`] catalogue fail with
+/// [`rusb::Error::Pipe`], the stall a USB device answers unknown requests
+/// with.
+///
+/// Displaying the simulator draws what it shows as ASCII art.
+///
+/// # Examples
+///
+/// ```
+/// use rx52::{X52ColoredLed, X52ColoredLedStatus, X52DeviceType, X52Driver};
+///
+/// let driver = X52Driver::new_simulated(&X52DeviceType::X52Pro);
+/// driver
+///     .toggle_led_colored(&X52ColoredLed::A, &X52ColoredLedStatus::Amber)
+///     .unwrap();
+/// let x52 = driver.transport();
+/// assert_eq!(x52.colored_led(&X52ColoredLed::A), X52ColoredLedStatus::Amber);
+/// println!("{x52}");
+/// ```
+#[derive(Debug)]
+pub struct X52Simulator {
+	x52_type: &'static X52DeviceType,
+	device: Mutex<SimulatedDevice>,
+	reports: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl X52Simulator {
+	/// Creates a simulated X52 of the given type, with everything off
+	pub fn new(x52_type: &'static X52DeviceType) -> Self {
+		Self {
+			x52_type,
+			device: Mutex::new(SimulatedDevice::new()),
+			reports: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// The type of X52 being simulated
+	pub fn x52_type(&self) -> &'static X52DeviceType {
+		self.x52_type
+	}
+
+	fn device(&self) -> MutexGuard<'_, SimulatedDevice> {
+		self.device.lock().unwrap()
+	}
+
+	/// Queues an input report to be returned by the next read
+	pub fn push_report(&self, report: Vec<u8>) {
+		self.reports.lock().unwrap().push_back(report)
+	}
+
+	/// Turns the simulated X52 off and on again, forgetting what it showed
+	pub fn power_cycle(&self) {
+		*self.device() = SimulatedDevice::new();
+	}
+
+	/// The color of a colored LED
+	pub fn colored_led(&self, led: &X52ColoredLed) -> X52ColoredLedStatus {
+		let (red, green) = led.led_ids();
+		let device = self.device();
+		X52ColoredLedStatus::from_led_states(
+			device.leds[red as usize],
+			device.leds[green as usize],
+		)
+	}
+
+	/// Whether an on/off LED is on
+	pub fn on_off_led(&self, led: &X52OnOffLed) -> X52OnOffLedStatus {
+		X52OnOffLedStatus::from_on(self.device().leds[led.led_id() as usize])
+	}
+
+	/// The text written to a line of the MFD since it was last cleared,
+	/// including the padding used to center it
+	pub fn mfd_text(&self, line: &X52MFDLine) -> String {
+		String::from_utf8_lossy(&self.device().mfd_text[*line as usize])
+			.into_owned()
+	}
+
+	/// The brightness of the LEDs
+	pub fn led_brightness(&self) -> u8 {
+		self.device().led_brightness
+	}
+
+	/// The brightness of the MFD
+	pub fn mfd_brightness(&self) -> u8 {
+		self.device().mfd_brightness
+	}
+
+	/// Whether the shift indicator on the MFD is on
+	pub fn shift_status(&self) -> bool {
+		self.device().shift_status
+	}
+
+	/// Whether the throttle and POV hat LEDs are blinking
+	pub fn blink_status(&self) -> bool {
+		self.device().blink_status
+	}
+
+	/// The (hour, minute) clock 1 was last set to
+	pub fn clock_1(&self) -> (u8, u8) {
+		self.device().clock_1
+	}
+
+	/// The offset in minutes of a clock from clock 1, which is 0 for clock 1
+	pub fn clock_offset(&self, clock: &X52Clocks) -> i16 {
+		match clock {
+			X52Clocks::Clock1 => 0,
+			X52Clocks::Clock2 => self.device().clock_offsets[0],
+			X52Clocks::Clock3 => self.device().clock_offsets[1],
+		}
+	}
+
+	/// The format a clock is shown in
+	pub fn clock_format(&self, clock: &X52Clocks) -> X52ClockFormat {
+		match self.device().clock_24h[*clock as usize] {
+			true => X52ClockFormat::Hr24,
+			false => X52ClockFormat::Hr12,
+		}
+	}
+
+	/// The (hour, minute) a clock shows, which is clock 1 moved by the
+	/// clock's offset
+	pub fn clock_time(&self, clock: &X52Clocks) -> (u8, u8) {
+		let (hour, minute) = self.clock_1();
+		let minutes = (hour as i32 * 60
+			+ minute as i32
+			+ self.clock_offset(clock) as i32)
+			.rem_euclid(24 * 60);
+		((minutes / 60) as u8, (minutes % 60) as u8)
+	}
+
+	/// The three fields of the date, from left to right
+	///
+	/// Which is the day, month and year depends on the
+	/// [`X52DateFormat`](crate::X52DateFormat) the driver was given.
+	pub fn date_fields(&self) -> (u8, u8, u8) {
+		self.device().date_fields
+	}
+
+	/// Draws a clock the way the MFD shows it
+	fn render_clock(&self, clock: &X52Clocks) -> String {
+		let (hour, minute) = self.clock_time(clock);
+		match self.clock_format(clock) {
+			X52ClockFormat::Hr24 => format!("{hour:02}:{minute:02}"),
+			X52ClockFormat::Hr12 => format!(
+				"{:2}:{minute:02}{}",
+				(hour + 11) % 12 + 1,
+				if hour < 12 { "am" } else { "pm" }
+			),
+		}
+	}
+}
+
+impl X52Transport for X52Simulator {
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error> {
+		let command = X52VendorCommand::decode(index, value)
+			.ok_or(Error::from(rusb::Error::Pipe))?;
+		// Commands the model can't act on, like LEDs on an X52 which is not a
+		// Pro, are ignored
+		if self.x52_type.capabilities().supports(command.capability()) {
+			self.device().handle(command);
+		}
+		Ok(())
+	}
+
+	fn read_report(
+		&self,
+		buf: &mut [u8],
+		_timeout: Duration,
+	) -> Result<usize, Error> {
+		let report = self
+			.reports
+			.lock()
+			.unwrap()
+			.pop_front()
+			.ok_or(Error::from(rusb::Error::Timeout))?;
+		let len = report.len().min(buf.len());
+		buf[..len].copy_from_slice(&report[..len]);
+		Ok(len)
+	}
+}
+
+/// How a colored LED is drawn
+fn colored_symbol(status: X52ColoredLedStatus) -> char {
+	match status {
+		X52ColoredLedStatus::Off => '-',
+		X52ColoredLedStatus::Red => 'R',
+		X52ColoredLedStatus::Green => 'G',
+		X52ColoredLedStatus::Amber => 'A',
+	}
+}
+
+/// How an on/off LED is drawn
+fn on_off_symbol(status: X52OnOffLedStatus) -> char {
+	match status {
+		X52OnOffLedStatus::Off => '-',
+		X52OnOffLedStatus::On => '*',
+	}
+}
+
+/// How an on/off setting is written
+fn on_off_text(enabled: bool) -> &'static str {
+	if enabled {
+		"on"
+	} else {
+		"off"
+	}
+}
+
+impl Display for X52Simulator {
+	/// Draws the stick and the throttle, with LEDs as `-` when off, `R`,
+	/// `G` and `A` for red, green and amber, and `*` when on
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+		let colored = |led| colored_symbol(self.colored_led(&led));
+		let on_off = |led| on_off_symbol(self.on_off_led(&led));
+		let mfd = |line| format!("|{:<16}|", self.mfd_text(&line));
+		let (first, second, last) = self.date_fields();
+
+		let stick = [
+			"       STICK".to_string(),
+			"   .-----------.".to_string(),
+			format!("   |  Fire  {}  |", on_off(X52OnOffLed::Fire)),
+			format!(
+				"   | A {}   B {} |",
+				colored(X52ColoredLed::A),
+				colored(X52ColoredLed::B)
+			),
+			format!("   |   POV {}   |", colored(X52ColoredLed::PovHat)),
+			"   '-----------'".to_string(),
+			format!(
+				"  T1 {}  T3 {}  T5 {}",
+				colored(X52ColoredLed::T1),
+				colored(X52ColoredLed::T3),
+				colored(X52ColoredLed::T5)
+			),
+		];
+		let throttle = [
+			"        THROTTLE".to_string(),
+			format!(
+				"  D {}  E {}  i {}  Throttle {}",
+				colored(X52ColoredLed::D),
+				colored(X52ColoredLed::E),
+				colored(X52ColoredLed::Clutch),
+				on_off(X52OnOffLed::Throttle)
+			),
+			"  +----------------+".to_string(),
+			format!("  {}", mfd(X52MFDLine::Line1)),
+			format!("  {}", mfd(X52MFDLine::Line2)),
+			format!("  {}", mfd(X52MFDLine::Line3)),
+			"  +----------------+".to_string(),
+		];
+		for (left, right) in stick.iter().zip(&throttle) {
+			writeln!(fmt, "{left:<24}{right}")?;
+		}
+		writeln!(
+			fmt,
+			"Clocks {} {} {}  Date {first:02}-{second:02}-{last:02}",
+			self.render_clock(&X52Clocks::Clock1),
+			self.render_clock(&X52Clocks::Clock2),
+			self.render_clock(&X52Clocks::Clock3),
+		)?;
+		write!(
+			fmt,
+			"Shift {}  Blink {}  LED brightness {}  MFD brightness {}",
+			on_off_text(self.shift_status()),
+			on_off_text(self.blink_status()),
+			self.led_brightness(),
+			self.mfd_brightness()
+		)
+	}
+}
+
+impl X52Driver<X52Simulator> {
+	/// Creates an X52Driver for a new [`X52Simulator`] of the given type
+	pub fn new_simulated(x52_type: &'static X52DeviceType) -> Self {
+		Self::new(X52Simulator::new(x52_type), x52_type)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::X52DateFormat;
+
+	#[test]
+	fn keeps_what_the_driver_sets() {
+		let driver = X52Driver::new_simulated(&X52DeviceType::X52Pro);
+		driver
+			.toggle_led_colored(
+				&X52ColoredLed::Clutch,
+				&X52ColoredLedStatus::Red,
+			)
+			.unwrap();
+		driver
+			.toggle_led_on_off(&X52OnOffLed::Throttle, &X52OnOffLedStatus::On)
+			.unwrap();
+		driver
+			.set_mfd_text(&X52MFDLine::Line2, "Hello".to_string())
+			.unwrap();
+		driver.set_clock_1(23, 30, false).unwrap();
+		driver.set_clock_2_offset(60, true).unwrap();
+		driver.set_date(31, 12, 24, X52DateFormat::YYMMDD).unwrap();
+
+		let x52 = driver.transport();
+		assert_eq!(
+			x52.colored_led(&X52ColoredLed::Clutch),
+			X52ColoredLedStatus::Red
+		);
+		assert_eq!(
+			x52.on_off_led(&X52OnOffLed::Throttle),
+			X52OnOffLedStatus::On
+		);
+		assert_eq!(x52.mfd_text(&X52MFDLine::Line2).trim(), "Hello");
+		assert_eq!(x52.mfd_text(&X52MFDLine::Line2).len(), MFD_LINE_SIZE);
+		assert_eq!(x52.clock_time(&X52Clocks::Clock2), (0, 30));
+		assert_eq!(x52.clock_format(&X52Clocks::Clock1), X52ClockFormat::Hr12);
+		assert_eq!(x52.date_fields(), (24, 12, 31));
+	}
+
+	#[test]
+	fn non_pro_ignores_leds() {
+		let x52 = X52Simulator::new(&X52DeviceType::X52);
+		let (red, value) =
+			X52VendorCommand::SetLed { id: 2, on: true }.encode();
+		x52.vendor_command(red, value).unwrap();
+		assert_eq!(
+			x52.colored_led(&X52ColoredLed::A),
+			X52ColoredLedStatus::Off
+		);
+	}
+
+	#[test]
+	fn unknown_requests_stall() {
+		let x52 = X52Simulator::new(&X52DeviceType::X52Pro);
+		let err = x52.vendor_command(0x1234, 0).unwrap_err();
+		assert_eq!(err.rusb_error(), Some(rusb::Error::Pipe));
+	}
+
+	#[test]
+	fn renders_the_mfd() {
+		let driver = X52Driver::new_simulated(&X52DeviceType::X52Pro);
+		driver
+			.set_mfd_text(&X52MFDLine::Line1, "rx52".to_string())
+			.unwrap();
+		driver.set_clock_1(13, 5, false).unwrap();
+		let picture = driver.transport().to_string();
+		assert!(picture.contains("|      rx52      |"));
+		assert!(picture.contains(" 1:05pm"));
+	}
+}
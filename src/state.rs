@@ -0,0 +1,729 @@
+//! Remembering what an X52 is showing
+
+use crate::command::CommandSlot;
+use crate::{
+	Error, UsbTransport, X52ClockFormat, X52Clocks, X52ColoredLed,
+	X52ColoredLedStatus, X52Command, X52DateFormat, X52Driver, X52MFDLine,
+	X52OnOffLed, X52OnOffLedStatus, X52Transport,
+};
+
+/// Everything an X52 can visibly show
+///
+/// The X52 can't report what it is showing, so this is built up from the
+/// commands sent to it with [`X52State::apply`]. A freshly created state has
+/// every LED off and a blank MFD, which is not necessarily what the X52 shows
+/// until the state has been sent to it. The brightnesses, clock 1 and the
+/// date are left unset, and unset parts are never sent, so sending a fresh
+/// state doesn't turn the X52 dark or reset its clock.
+///
+/// A state doubles as a snapshot of an X52, which can be reproduced with
+/// [`X52Driver::apply_snapshot`]. With the `serde` feature it can be
+/// serialized, for example to save scenes to disk. LEDs and MFD lines are
+/// written as maps keyed by their names, and anything left out when
+/// deserializing takes its default value, or is left unset.
+///
+/// # Examples
+///
+/// ```
+/// use rx52::{MockTransport, X52Command, X52DeviceType, X52Driver, X52State};
+///
+/// let mut scene = X52State::new();
+/// scene.apply(&X52Command::SetMfdBrightness(80));
+///
+/// let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52Pro);
+/// driver.apply_snapshot(&scene).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct X52State {
+	#[cfg_attr(feature = "serde", serde(with = "keyed::colored_leds"))]
+	colored_leds: [X52ColoredLedStatus; 9],
+	#[cfg_attr(feature = "serde", serde(with = "keyed::on_off_leds"))]
+	on_off_leds: [X52OnOffLedStatus; 2],
+	#[cfg_attr(feature = "serde", serde(with = "keyed::mfd_text"))]
+	mfd_text: [String; 3],
+	led_brightness: Option<u8>,
+	mfd_brightness: Option<u8>,
+	shift_status: bool,
+	blink_status: bool,
+	clock_1: Option<(u8, u8)>,
+	clock_offsets: [i16; 2],
+	clock_formats: [X52ClockFormat; 3],
+	date: Option<(u8, u8, u8)>,
+	date_format: X52DateFormat,
+}
+
+impl Default for X52State {
+	fn default() -> Self {
+		Self {
+			colored_leds: [X52ColoredLedStatus::Off; 9],
+			on_off_leds: [X52OnOffLedStatus::Off; 2],
+			mfd_text: Default::default(),
+			led_brightness: None,
+			mfd_brightness: None,
+			shift_status: false,
+			blink_status: false,
+			clock_1: None,
+			clock_offsets: [0; 2],
+			clock_formats: [X52ClockFormat::Hr24; 3],
+			date: None,
+			date_format: X52DateFormat::DDMMYY,
+		}
+	}
+}
+
+/// (De)serializing the arrays in [`X52State`] as maps keyed by what each
+/// element is for
+#[cfg(feature = "serde")]
+mod keyed {
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+	use std::collections::HashMap;
+	use std::hash::Hash;
+
+	fn serialize<S, K, V, const N: usize>(
+		keys: &[K; N],
+		values: &[V; N],
+		serializer: S,
+	) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+		K: Serialize,
+		V: Serialize,
+	{
+		serializer.collect_map(keys.iter().zip(values))
+	}
+
+	fn deserialize<'de, D, K, V, const N: usize>(
+		keys: &[K; N],
+		default: V,
+		deserializer: D,
+	) -> Result<[V; N], D::Error>
+	where
+		D: Deserializer<'de>,
+		K: Deserialize<'de> + Eq + Hash,
+		V: Deserialize<'de> + Clone,
+	{
+		let mut map = HashMap::<K, V>::deserialize(deserializer)?;
+		Ok(std::array::from_fn(|i| {
+			map.remove(&keys[i]).unwrap_or_else(|| default.clone())
+		}))
+	}
+
+	macro_rules! keyed {
+		($name:ident, $key:ty, $value:ty, $n:literal, $default:expr) => {
+			pub mod $name {
+				use super::*;
+
+				pub fn serialize<S: Serializer>(
+					values: &[$value; $n],
+					serializer: S,
+				) -> Result<S::Ok, S::Error> {
+					super::serialize(&<$key>::ALL, values, serializer)
+				}
+
+				pub fn deserialize<'de, D: Deserializer<'de>>(
+					deserializer: D,
+				) -> Result<[$value; $n], D::Error> {
+					super::deserialize(&<$key>::ALL, $default, deserializer)
+				}
+			}
+		};
+	}
+
+	keyed!(
+		colored_leds,
+		crate::X52ColoredLed,
+		crate::X52ColoredLedStatus,
+		9,
+		crate::X52ColoredLedStatus::Off
+	);
+	keyed!(
+		on_off_leds,
+		crate::X52OnOffLed,
+		crate::X52OnOffLedStatus,
+		2,
+		crate::X52OnOffLedStatus::Off
+	);
+	keyed!(mfd_text, crate::X52MFDLine, String, 3, String::new());
+}
+
+/// Maps the `use_24h` argument of the clock commands to a format
+fn clock_format(use_24h: bool) -> X52ClockFormat {
+	if use_24h {
+		X52ClockFormat::Hr24
+	} else {
+		X52ClockFormat::Hr12
+	}
+}
+
+impl X52State {
+	/// Creates a state with everything off, and the brightnesses, clock 1
+	/// and the date unset
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The color of a colored LED
+	pub fn colored_led(&self, led: &X52ColoredLed) -> X52ColoredLedStatus {
+		self.colored_leds[*led as usize]
+	}
+
+	/// Whether an on/off LED is on
+	pub fn on_off_led(&self, led: &X52OnOffLed) -> X52OnOffLedStatus {
+		self.on_off_leds[*led as usize]
+	}
+
+	/// The text on a line of the MFD, without the padding used to center it
+	pub fn mfd_text(&self, line: &X52MFDLine) -> &str {
+		&self.mfd_text[*line as usize]
+	}
+
+	/// The brightness of the LEDs, if it was set
+	pub fn led_brightness(&self) -> Option<u8> {
+		self.led_brightness
+	}
+
+	/// The brightness of the MFD, if it was set
+	pub fn mfd_brightness(&self) -> Option<u8> {
+		self.mfd_brightness
+	}
+
+	/// Whether the shift indicator on the MFD is on
+	pub fn shift_status(&self) -> bool {
+		self.shift_status
+	}
+
+	/// Whether the throttle and POV hat LEDs are blinking
+	pub fn blink_status(&self) -> bool {
+		self.blink_status
+	}
+
+	/// The (hour, minute) clock 1 was last set to, if it was set
+	pub fn clock_1(&self) -> Option<(u8, u8)> {
+		self.clock_1
+	}
+
+	/// The offset in minutes of a clock from clock 1, which is 0 for clock 1
+	pub fn clock_offset(&self, clock: &X52Clocks) -> i16 {
+		match clock {
+			X52Clocks::Clock1 => 0,
+			X52Clocks::Clock2 => self.clock_offsets[0],
+			X52Clocks::Clock3 => self.clock_offsets[1],
+		}
+	}
+
+	/// The format a clock is shown in
+	pub fn clock_format(&self, clock: &X52Clocks) -> X52ClockFormat {
+		self.clock_formats[*clock as usize]
+	}
+
+	/// The (day, month, year) shown on the MFD, if it was set
+	pub fn date(&self) -> Option<(u8, u8, u8)> {
+		self.date
+	}
+
+	/// The order the date is shown in
+	pub fn date_format(&self) -> X52DateFormat {
+		self.date_format
+	}
+
+	/// Updates the state as if `command` had been sent to the X52
+	///
+	/// Returns true if anything changed. The command is not validated, see
+	/// [`X52Command::validate`].
+	pub fn apply(&mut self, command: &X52Command) -> bool {
+		let old = self.clone();
+		match command {
+			X52Command::SetColoredLed(led, status) => {
+				self.colored_leds[*led as usize] = *status
+			}
+			X52Command::SetOnOffLed(led, status) => {
+				self.on_off_leds[*led as usize] = *status
+			}
+			X52Command::SetMfdText(line, text) => {
+				self.mfd_text[*line as usize] = text.clone()
+			}
+			X52Command::ClearMfdLine(line) => {
+				self.mfd_text[*line as usize].clear()
+			}
+			X52Command::SetLedBrightness(x) => self.led_brightness = Some(*x),
+			X52Command::SetMfdBrightness(x) => self.mfd_brightness = Some(*x),
+			X52Command::SetShiftStatus(x) => self.shift_status = *x,
+			X52Command::SetBlinkStatus(x) => self.blink_status = *x,
+			X52Command::SetClock1 {
+				hour,
+				minute,
+				use_24h,
+			} => {
+				self.clock_1 = Some((*hour, *minute));
+				self.clock_formats[0] = clock_format(*use_24h);
+			}
+			X52Command::SetClock2Offset { offset, use_24h } => {
+				self.clock_offsets[0] = *offset;
+				self.clock_formats[1] = clock_format(*use_24h);
+			}
+			X52Command::SetClock3Offset { offset, use_24h } => {
+				self.clock_offsets[1] = *offset;
+				self.clock_formats[2] = clock_format(*use_24h);
+			}
+			X52Command::SetDate {
+				day,
+				month,
+				year,
+				format,
+			} => {
+				self.date = Some((*day, *month, *year));
+				self.date_format = *format;
+			}
+		}
+		*self != old
+	}
+
+	/// The command which makes the given part of the X52 match this state,
+	/// or [`None`] if that part is unset
+	pub(crate) fn command_for(&self, slot: CommandSlot) -> Option<X52Command> {
+		let use_24h = |clock: X52Clocks| {
+			self.clock_format(&clock) == X52ClockFormat::Hr24
+		};
+		Some(match slot {
+			CommandSlot::ColoredLed(led) => {
+				X52Command::SetColoredLed(led, self.colored_led(&led))
+			}
+			CommandSlot::OnOffLed(led) => {
+				X52Command::SetOnOffLed(led, self.on_off_led(&led))
+			}
+			CommandSlot::MfdLine(line) => match self.mfd_text(&line) {
+				"" => X52Command::ClearMfdLine(line),
+				text => X52Command::SetMfdText(line, text.to_string()),
+			},
+			CommandSlot::LedBrightness => {
+				X52Command::SetLedBrightness(self.led_brightness?)
+			}
+			CommandSlot::MfdBrightness => {
+				X52Command::SetMfdBrightness(self.mfd_brightness?)
+			}
+			CommandSlot::ShiftStatus => {
+				X52Command::SetShiftStatus(self.shift_status)
+			}
+			CommandSlot::BlinkStatus => {
+				X52Command::SetBlinkStatus(self.blink_status)
+			}
+			CommandSlot::Clock1 => {
+				let (hour, minute) = self.clock_1?;
+				X52Command::SetClock1 {
+					hour,
+					minute,
+					use_24h: use_24h(X52Clocks::Clock1),
+				}
+			}
+			CommandSlot::Clock2 => X52Command::SetClock2Offset {
+				offset: self.clock_offsets[0],
+				use_24h: use_24h(X52Clocks::Clock2),
+			},
+			CommandSlot::Clock3 => X52Command::SetClock3Offset {
+				offset: self.clock_offsets[1],
+				use_24h: use_24h(X52Clocks::Clock3),
+			},
+			CommandSlot::Date => {
+				let (day, month, year) = self.date?;
+				X52Command::SetDate {
+					day,
+					month,
+					year,
+					format: self.date_format,
+				}
+			}
+		})
+	}
+
+	/// The commands which make an X52 show exactly this state
+	///
+	/// Unset parts are left out.
+	pub fn commands(&self) -> Vec<X52Command> {
+		CommandSlot::all()
+			.filter_map(|x| self.command_for(x))
+			.collect()
+	}
+}
+
+impl<T: X52Transport> X52Driver<T> {
+	/// Makes the X52 show exactly what is in `snapshot`
+	///
+	/// Every part of the X52 is sent, whether it changed or not. Parts which
+	/// are unset in `snapshot`, and parts the X52 doesn't have, like the LEDs
+	/// of an X52 which is not a Pro, are skipped.
+	pub fn apply_snapshot(&self, snapshot: &X52State) -> Result<(), Error> {
+		for command in snapshot.commands() {
+			if self.supports(command.capability()) {
+				self.execute(&command)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An [`X52Driver`] which remembers what it has sent to the X52
+///
+/// Changes made through the shadow are only stored in memory until
+/// [`X52Shadow::flush`] is called, which then sends only the commands needed
+/// for the parts of the X52 that actually changed since the last flush.
+/// Setting something to the value it already has sends nothing at all.
+///
+/// # Examples
+///
+/// ```
+/// use rx52::{
+///     MockTransport, X52ColoredLed, X52ColoredLedStatus, X52DeviceType,
+///     X52Driver, X52Shadow,
+/// };
+///
+/// let mut shadow = X52Shadow::new(X52Driver::new(
+///     MockTransport::new(),
+///     &X52DeviceType::X52Pro,
+/// ));
+/// shadow.set_colored_led(&X52ColoredLed::T3, &X52ColoredLedStatus::Amber);
+/// shadow.set_led_brightness(100);
+/// shadow.flush().unwrap();
+///
+/// assert_eq!(
+///     shadow.state().colored_led(&X52ColoredLed::T3),
+///     X52ColoredLedStatus::Amber
+/// );
+/// ```
+pub struct X52Shadow<T: X52Transport = UsbTransport> {
+	driver: X52Driver<T>,
+	state: X52State,
+	dirty: Vec<CommandSlot>,
+}
+
+impl<T: X52Transport> X52Shadow<T> {
+	/// Wraps a driver, assuming the X52 shows the default [`X52State`]
+	///
+	/// Use [`X52Shadow::mark_all_dirty`] to send the whole state with the
+	/// next flush.
+	pub fn new(driver: X52Driver<T>) -> Self {
+		Self::with_state(driver, X52State::default())
+	}
+
+	/// Wraps a driver, assuming the X52 shows `state`
+	pub fn with_state(driver: X52Driver<T>, state: X52State) -> Self {
+		Self {
+			driver,
+			state,
+			dirty: Vec::new(),
+		}
+	}
+
+	/// The driver underneath
+	pub fn driver(&self) -> &X52Driver<T> {
+		&self.driver
+	}
+
+	/// Gives the driver back, dropping any changes which were not flushed
+	pub fn into_driver(self) -> X52Driver<T> {
+		self.driver
+	}
+
+	/// The state of the X52 after the next flush
+	pub fn state(&self) -> &X52State {
+		&self.state
+	}
+
+	/// Returns true if there are changes which have not been flushed
+	pub fn is_dirty(&self) -> bool {
+		!self.dirty.is_empty()
+	}
+
+	/// Sends the whole state with the next flush, even if nothing changed
+	pub fn mark_all_dirty(&mut self) {
+		self.dirty = CommandSlot::all().collect();
+	}
+
+	/// Applies a command to the state, to be sent with the next flush
+	///
+	/// Fails without changing anything if the command is invalid.
+	pub fn update(&mut self, command: X52Command) -> Result<(), Error> {
+		command.validate()?;
+		let slot = command.slot();
+		if self.state.apply(&command) && !self.dirty.contains(&slot) {
+			self.dirty.push(slot);
+		}
+		Ok(())
+	}
+
+	/// Sends the commands for everything changed since the last flush
+	///
+	/// Stops at the first command which fails. Whatever was not sent yet,
+	/// including the failed command, is tried again with the next flush.
+	/// Parts the X52 doesn't have, like the LEDs of an X52 which is not a
+	/// Pro, are skipped like in [`X52Driver::apply_snapshot`].
+	pub fn flush(&mut self) -> Result<(), Error> {
+		// Send in a fixed order, so clock 1 is set before the offsets
+		let dirty = CommandSlot::all()
+			.filter(|x| self.dirty.contains(x))
+			.collect::<Vec<_>>();
+		for slot in dirty {
+			let command = self.state.command_for(slot);
+			if let Some(command) =
+				command.filter(|x| self.driver.supports(x.capability()))
+			{
+				self.driver.execute(&command)?;
+			}
+			self.dirty.retain(|x| *x != slot);
+		}
+		Ok(())
+	}
+
+	/// Sets the color of a colored LED
+	pub fn set_colored_led(
+		&mut self,
+		led: &X52ColoredLed,
+		status: &X52ColoredLedStatus,
+	) {
+		// Only text and clock offsets can fail to validate
+		let _ = self.update(X52Command::SetColoredLed(*led, *status));
+	}
+
+	/// Turns an on/off LED on or off
+	pub fn set_on_off_led(
+		&mut self,
+		led: &X52OnOffLed,
+		status: &X52OnOffLedStatus,
+	) {
+		let _ = self.update(X52Command::SetOnOffLed(*led, *status));
+	}
+
+	/// Sets the text of a line of the MFD
+	pub fn set_mfd_text(
+		&mut self,
+		line: &X52MFDLine,
+		text: String,
+	) -> Result<(), Error> {
+		self.update(X52Command::SetMfdText(*line, text))
+	}
+
+	/// Clears a line of the MFD
+	pub fn clear_mfd_line(&mut self, line: &X52MFDLine) {
+		let _ = self.update(X52Command::ClearMfdLine(*line));
+	}
+
+	/// Sets the brightness of the LEDs
+	pub fn set_led_brightness(&mut self, brightness: u8) {
+		let _ = self.update(X52Command::SetLedBrightness(brightness));
+	}
+
+	/// Sets the brightness of the MFD
+	pub fn set_mfd_brightness(&mut self, brightness: u8) {
+		let _ = self.update(X52Command::SetMfdBrightness(brightness));
+	}
+
+	/// Sets the shift indicator on the MFD
+	pub fn set_shift_status(&mut self, enabled: bool) {
+		let _ = self.update(X52Command::SetShiftStatus(enabled));
+	}
+
+	/// Sets the blinking of the throttle and POV hat LEDs
+	pub fn set_blink_status(&mut self, enabled: bool) {
+		let _ = self.update(X52Command::SetBlinkStatus(enabled));
+	}
+
+	/// Sets clock 1
+	pub fn set_clock_1(&mut self, hour: u8, minute: u8, use_24h: bool) {
+		let _ = self.update(X52Command::SetClock1 {
+			hour,
+			minute,
+			use_24h,
+		});
+	}
+
+	/// Sets the offset in minutes of clock 2 from clock 1
+	pub fn set_clock_2_offset(
+		&mut self,
+		offset: i16,
+		use_24h: bool,
+	) -> Result<(), Error> {
+		self.update(X52Command::SetClock2Offset { offset, use_24h })
+	}
+
+	/// Sets the offset in minutes of clock 3 from clock 1
+	pub fn set_clock_3_offset(
+		&mut self,
+		offset: i16,
+		use_24h: bool,
+	) -> Result<(), Error> {
+		self.update(X52Command::SetClock3Offset { offset, use_24h })
+	}
+
+	/// Sets the date shown on the MFD
+	pub fn set_date(
+		&mut self,
+		day: u8,
+		month: u8,
+		year: u8,
+		format: X52DateFormat,
+	) {
+		let _ = self.update(X52Command::SetDate {
+			day,
+			month,
+			year,
+			format,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ErrorId, MockTransport, X52DeviceType};
+
+	fn mock_shadow() -> X52Shadow<MockTransport> {
+		X52Shadow::new(X52Driver::new(
+			MockTransport::new(),
+			&X52DeviceType::X52Pro,
+		))
+	}
+
+	#[test]
+	fn flush_skips_leds_on_non_pro() {
+		let mut shadow = X52Shadow::new(X52Driver::new(
+			MockTransport::new(),
+			&X52DeviceType::X52,
+		));
+		shadow.set_colored_led(&X52ColoredLed::A, &X52ColoredLedStatus::Red);
+		shadow.set_led_brightness(100);
+		shadow.flush().unwrap();
+		assert!(!shadow.is_dirty());
+		assert_eq!(shadow.driver().transport().requests(), vec![(0xB2, 100)]);
+
+		shadow.mark_all_dirty();
+		shadow.flush().unwrap();
+		let requests = shadow.driver().transport().requests();
+		assert!(requests.iter().all(|(index, _)| *index != 0xB8));
+		assert!(requests.len() > 1);
+	}
+
+	#[test]
+	fn flush_sends_only_changes() {
+		let mut shadow = mock_shadow();
+		for status in [X52ColoredLedStatus::Red, X52ColoredLedStatus::Green] {
+			shadow.set_colored_led(&X52ColoredLed::A, &status);
+		}
+		// Already the default, so nothing to send
+		shadow.set_shift_status(false);
+		shadow.flush().unwrap();
+		assert_eq!(
+			shadow.driver().transport().requests(),
+			vec![(0xB8, 0x0200), (0xB8, 0x0301)]
+		);
+
+		shadow.driver().transport().clear();
+		shadow.flush().unwrap();
+		assert!(shadow.driver().transport().requests().is_empty());
+	}
+
+	#[test]
+	fn set_back_before_flush_still_sends() {
+		let mut shadow = mock_shadow();
+		shadow.set_led_brightness(20);
+		shadow.set_led_brightness(0);
+		assert!(shadow.is_dirty());
+		shadow.flush().unwrap();
+		assert_eq!(shadow.driver().transport().requests(), vec![(0xB2, 0)]);
+	}
+
+	#[test]
+	fn invalid_update_changes_nothing() {
+		let mut shadow = mock_shadow();
+		let err = shadow.set_clock_2_offset(2000, true).unwrap_err();
+		assert_eq!(err.id(), Some(ErrorId::ClockOffsetTooBig));
+		assert!(!shadow.is_dirty());
+		assert_eq!(shadow.state().clock_offset(&X52Clocks::Clock2), 0);
+	}
+
+	#[test]
+	fn mark_all_dirty_sends_everything() {
+		let mut shadow = mock_shadow();
+		shadow.mark_all_dirty();
+		shadow.flush().unwrap();
+		let expected = X52State::default().commands();
+		let driver =
+			X52Driver::new(MockTransport::new(), &X52DeviceType::X52Pro);
+		for command in &expected {
+			driver.execute(command).unwrap();
+		}
+		assert_eq!(
+			shadow.driver().transport().requests(),
+			driver.transport().requests()
+		);
+	}
+
+	#[test]
+	fn unset_parts_are_not_sent() {
+		let mut snapshot = X52State::new();
+		assert!(snapshot.commands().iter().all(|x| !matches!(
+			x,
+			X52Command::SetLedBrightness(_)
+				| X52Command::SetMfdBrightness(_)
+				| X52Command::SetClock1 { .. }
+				| X52Command::SetDate { .. }
+		)));
+		snapshot.apply(&X52Command::SetMfdBrightness(60));
+		let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		driver.apply_snapshot(&snapshot).unwrap();
+		let requests = driver.transport().requests();
+		assert!(requests.contains(&(0xB1, 60)));
+		assert!(requests.iter().all(|x| x.0 != 0xB2 && x.0 != 0xC0));
+	}
+
+	#[test]
+	fn snapshot_skips_leds_on_non_pro() {
+		let mut snapshot = X52State::new();
+		snapshot.apply(&X52Command::SetColoredLed(
+			X52ColoredLed::E,
+			X52ColoredLedStatus::Red,
+		));
+		let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		driver.apply_snapshot(&snapshot).unwrap();
+		assert!(driver.transport().requests().iter().all(|x| x.0 != 0xB8));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn snapshot_serde_round_trip() {
+		let mut snapshot = X52State::new();
+		snapshot.apply(&X52Command::SetColoredLed(
+			X52ColoredLed::Clutch,
+			X52ColoredLedStatus::Amber,
+		));
+		snapshot.apply(&X52Command::SetMfdText(X52MFDLine::Line2, "Hi".into()));
+		snapshot.apply(&X52Command::SetClock2Offset {
+			offset: -60,
+			use_24h: false,
+		});
+		let json = serde_json::to_string(&snapshot).unwrap();
+		assert!(json.contains("\"Clutch\":\"Amber\""));
+		assert_eq!(serde_json::from_str::<X52State>(&json).unwrap(), snapshot);
+
+		let partial: X52State =
+			serde_json::from_str(r#"{"mfd_text": {"Line1": "Hello"}}"#)
+				.unwrap();
+		assert_eq!(partial.mfd_text(&X52MFDLine::Line1), "Hello");
+		assert_eq!(partial.led_brightness(), None);
+		assert_eq!(partial.clock_1(), None);
+	}
+
+	#[test]
+	fn state_apply_reports_changes() {
+		let mut state = X52State::new();
+		let command = X52Command::SetMfdText(X52MFDLine::Line3, "Hi".into());
+		assert!(state.apply(&command));
+		assert!(!state.apply(&command));
+		assert_eq!(state.mfd_text(&X52MFDLine::Line3), "Hi");
+		assert!(state.apply(&X52Command::ClearMfdLine(X52MFDLine::Line3)));
+		assert_eq!(state.mfd_text(&X52MFDLine::Line3), "");
+	}
+}
@@ -0,0 +1,359 @@
+//! Transports that carry commands between an [`X52Driver`](crate::X52Driver)
+//! and the physical (or not so physical) X52
+
+use crate::device::read_serial;
+use crate::diagnostics::{diagnose_kernel_driver, diagnose_usb};
+use crate::{Error, ErrorId, X52DeviceInfo};
+use rusb::{
+	request_type, Context, Device, DeviceHandle, Direction, Recipient,
+	RequestType,
+};
+use rx52_core::X52_VENDOR_REQUEST;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// The default timeout for vendor requests
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_millis(5000);
+/// The interface the X52 sends its input reports on
+const X52_HID_INTERFACE: u8 = 0;
+/// The interrupt endpoint the X52 sends its input reports on
+pub(crate) const X52_REPORT_ENDPOINT: u8 = 0x81;
+
+/// A way of sending commands to an X52
+///
+/// [`X52Driver`](crate::X52Driver) turns every high level operation into one
+/// or more vendor requests, each made up of an index and a value, and hands
+/// them to a transport. Implement this trait to run the driver over something
+/// other than libusb.
+pub trait X52Transport {
+	/// Sends a single vendor request to the X52
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error>;
+
+	/// Reads an input report from the X52 into `buf`
+	///
+	/// Returns the number of bytes read. Transports which can't read input
+	/// reports return [`ErrorId::NotSupported`], which is the default.
+	fn read_report(
+		&self,
+		_buf: &mut [u8],
+		_timeout: Duration,
+	) -> Result<usize, Error> {
+		Err(Error::new(
+			ErrorId::NotSupported,
+			"This transport can not read input reports".to_string(),
+		))
+	}
+
+	/// The (bus, device) of the X52, if the transport is backed by a USB bus
+	fn bus_device(&self) -> Option<(u8, u8)> {
+		None
+	}
+
+	/// Changes how long [`X52Transport::vendor_command`] waits for the X52
+	///
+	/// Transports without timeouts ignore this, which is the default.
+	fn set_timeout(&self, _timeout: Duration) {}
+}
+
+impl<T: X52Transport + ?Sized> X52Transport for Box<T> {
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error> {
+		(**self).vendor_command(index, value)
+	}
+
+	fn read_report(
+		&self,
+		buf: &mut [u8],
+		timeout: Duration,
+	) -> Result<usize, Error> {
+		(**self).read_report(buf, timeout)
+	}
+
+	fn bus_device(&self) -> Option<(u8, u8)> {
+		(**self).bus_device()
+	}
+
+	fn set_timeout(&self, timeout: Duration) {
+		(**self).set_timeout(timeout)
+	}
+}
+
+/// Does a vendor command on the given device handle
+fn do_vendor_command(
+	device: &DeviceHandle<Context>,
+	index: u16,
+	value: u16,
+	timeout: Duration,
+) -> Result<(), Error> {
+	device.write_control(
+		request_type(Direction::Out, RequestType::Vendor, Recipient::Device),
+		X52_VENDOR_REQUEST,
+		value,
+		index,
+		&[0_u8; 0], // Empty data
+		timeout,
+	)?;
+	Ok(())
+}
+
+/// What has been done to the handle of a [`UsbTransport`]
+struct UsbSession {
+	/// Interfaces claimed by us, which need releasing
+	claimed: Vec<u8>,
+	/// Interfaces whose kernel driver we detached, which need reattaching
+	detached: Vec<u8>,
+}
+
+impl UsbSession {
+	fn claim_interface(
+		&mut self,
+		handle: &mut DeviceHandle<Context>,
+		iface: u8,
+	) -> Result<(), Error> {
+		if self.claimed.contains(&iface) {
+			return Ok(());
+		}
+		// Not every platform can tell us about kernel drivers, in which case
+		// we just try to claim the interface anyways
+		if handle.kernel_driver_active(iface).unwrap_or(false) {
+			handle.detach_kernel_driver(iface).map_err(|x| {
+				diagnose_kernel_driver(x, &handle.device(), iface)
+			})?;
+			self.detached.push(iface);
+		}
+		handle
+			.claim_interface(iface)
+			.map_err(|x| diagnose_usb(x, &handle.device()))?;
+		self.claimed.push(iface);
+		Ok(())
+	}
+
+	fn release_interface(
+		&mut self,
+		handle: &mut DeviceHandle<Context>,
+		iface: u8,
+	) -> Result<(), Error> {
+		if let Some(index) = self.claimed.iter().position(|x| *x == iface) {
+			self.claimed.remove(index);
+			handle.release_interface(iface)?;
+		}
+		if let Some(index) = self.detached.iter().position(|x| *x == iface) {
+			self.detached.remove(index);
+			handle.attach_kernel_driver(iface)?;
+		}
+		Ok(())
+	}
+}
+
+/// A transport which talks to an X52 through libusb
+///
+/// The device is opened once when the transport is created and the handle is
+/// kept for as long as the transport lives. Any interfaces claimed along the
+/// way are released, and any kernel drivers detached are reattached, when it
+/// is dropped.
+pub struct UsbTransport {
+	device: Device<Context>,
+	/// Kept outside the session so reads don't hold up vendor requests, and
+	/// only written to claim and release interfaces
+	handle: RwLock<DeviceHandle<Context>>,
+	/// Only locked while the handle is not being waited for
+	session: Mutex<UsbSession>,
+	/// How long to wait for vendor requests, in milliseconds
+	timeout: AtomicU64,
+}
+
+impl UsbTransport {
+	/// Opens a session with the given USB device
+	///
+	/// This does not check that the device is actually an X52. Fails with
+	/// [`ErrorId::PermissionDenied`] or [`ErrorId::DeviceBusy`] if the device
+	/// can't be opened, see [`Error::hint`] for how to fix it.
+	pub fn open(device: Device<Context>) -> Result<Self, Error> {
+		let handle = device.open().map_err(|x| diagnose_usb(x, &device))?;
+		Ok(Self {
+			device,
+			handle: RwLock::new(handle),
+			session: Mutex::new(UsbSession {
+				claimed: Vec::new(),
+				detached: Vec::new(),
+			}),
+			timeout: AtomicU64::new(REQUEST_TIMEOUT.as_millis() as u64),
+		})
+	}
+
+	/// The underlying USB device
+	pub fn device(&self) -> &Device<Context> {
+		&self.device
+	}
+
+	/// Reads the serial number of the device, if it has one
+	pub(crate) fn serial(&self) -> Option<String> {
+		read_serial(&self.device, &self.handle.read().unwrap())
+	}
+
+	/// Reads everything USB says about the device
+	pub(crate) fn info(&self) -> Result<X52DeviceInfo, Error> {
+		X52DeviceInfo::read(&self.device, Some(&self.handle.read().unwrap()))
+	}
+
+	/// Claims an interface of the device for the rest of the session
+	///
+	/// If a kernel driver is bound to the interface, it is detached first and
+	/// reattached once the interface is released. Vendor commands do not need
+	/// any interface claimed, but reading input reports does, which
+	/// [`X52Transport::read_report`] takes care of.
+	pub fn claim_interface(&self, iface: u8) -> Result<(), Error> {
+		// Waiting for the handle would wait for any read in progress
+		if self.session.lock().unwrap().claimed.contains(&iface) {
+			return Ok(());
+		}
+		let mut handle = self.handle.write().unwrap();
+		self.session
+			.lock()
+			.unwrap()
+			.claim_interface(&mut handle, iface)
+	}
+
+	/// Releases an interface claimed with [`UsbTransport::claim_interface`]
+	///
+	/// Releasing an interface which is not claimed does nothing.
+	pub fn release_interface(&self, iface: u8) -> Result<(), Error> {
+		let mut handle = self.handle.write().unwrap();
+		self.session
+			.lock()
+			.unwrap()
+			.release_interface(&mut handle, iface)
+	}
+}
+
+impl Drop for UsbTransport {
+	fn drop(&mut self) {
+		// There is nobody left to report errors to, and the handle is closed
+		// right after anyways
+		let session = self.session.get_mut().unwrap();
+		let handle = self.handle.get_mut().unwrap();
+		for iface in session.claimed.clone() {
+			let _ = session.release_interface(handle, iface);
+		}
+		for iface in session.detached.clone() {
+			let _ = session.release_interface(handle, iface);
+		}
+	}
+}
+
+impl X52Transport for UsbTransport {
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error> {
+		let timeout =
+			Duration::from_millis(self.timeout.load(Ordering::Relaxed));
+		do_vendor_command(&self.handle.read().unwrap(), index, value, timeout)
+	}
+
+	/// Reads an input report from the X52 into `buf`
+	///
+	/// <div class="warning">This claims the X52's HID interface for the rest
+	/// of the session, which stops the operating system from seeing the
+	/// joystick's input until the interface is released.</div>
+	fn read_report(
+		&self,
+		buf: &mut [u8],
+		timeout: Duration,
+	) -> Result<usize, Error> {
+		self.claim_interface(X52_HID_INTERFACE)?;
+		// The session isn't locked while waiting, so commands can still be
+		// sent from other threads
+		Ok(self.handle.read().unwrap().read_interrupt(
+			X52_REPORT_ENDPOINT,
+			buf,
+			timeout,
+		)?)
+	}
+
+	fn bus_device(&self) -> Option<(u8, u8)> {
+		Some((self.device.bus_number(), self.device.address()))
+	}
+
+	fn set_timeout(&self, timeout: Duration) {
+		let millis = timeout.as_millis().min(u64::MAX as u128) as u64;
+		self.timeout.store(millis, Ordering::Relaxed);
+	}
+}
+
+/// An in-memory transport which records every request sent to it
+///
+/// Useful for testing code built on [`X52Driver`](crate::X52Driver) on
+/// machines without an X52 plugged in.
+///
+/// # Examples
+///
+/// ```
+/// use rx52::{MockTransport, X52DeviceType, X52Driver};
+///
+/// let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52Pro);
+/// driver.set_led_brightness(64).unwrap();
+/// assert_eq!(driver.transport().requests(), vec![(0xB2, 64)]);
+/// ```
+#[derive(Default)]
+pub struct MockTransport {
+	requests: Mutex<Vec<(u16, u16)>>,
+	reports: Mutex<VecDeque<Vec<u8>>>,
+	outcomes: Mutex<VecDeque<Result<(), rusb::Error>>>,
+}
+
+impl MockTransport {
+	/// Creates a mock transport with no recorded requests
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Every (index, value) pair sent so far, oldest first
+	pub fn requests(&self) -> Vec<(u16, u16)> {
+		self.requests.lock().unwrap().clone()
+	}
+
+	/// Forgets all recorded requests
+	pub fn clear(&self) {
+		self.requests.lock().unwrap().clear()
+	}
+
+	/// Queues an input report to be returned by the next read
+	pub fn push_report(&self, report: Vec<u8>) {
+		self.reports.lock().unwrap().push_back(report)
+	}
+
+	/// Queues the result of an upcoming request, to simulate failures
+	///
+	/// Requests succeed once the queue is empty. Failed requests are not
+	/// recorded.
+	pub fn push_outcome(&self, outcome: Result<(), rusb::Error>) {
+		self.outcomes.lock().unwrap().push_back(outcome)
+	}
+}
+
+impl X52Transport for MockTransport {
+	fn vendor_command(&self, index: u16, value: u16) -> Result<(), Error> {
+		self.outcomes
+			.lock()
+			.unwrap()
+			.pop_front()
+			.unwrap_or(Ok(()))?;
+		self.requests.lock().unwrap().push((index, value));
+		Ok(())
+	}
+
+	fn read_report(
+		&self,
+		buf: &mut [u8],
+		_timeout: Duration,
+	) -> Result<usize, Error> {
+		let report = self
+			.reports
+			.lock()
+			.unwrap()
+			.pop_front()
+			.ok_or(Error::from(rusb::Error::Timeout))?;
+		let len = report.len().min(buf.len());
+		buf[..len].copy_from_slice(&report[..len]);
+		Ok(len)
+	}
+}
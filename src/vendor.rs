@@ -0,0 +1,74 @@
+//! Sending vendor requests to the X52 directly
+//!
+//! The catalogue of requests itself is [`X52VendorCommand`], from the
+//! `rx52_core` crate.
+
+use crate::{Error, X52Driver, X52Transport, X52VendorCommand};
+
+impl<T: X52Transport> X52Driver<T> {
+	/// Sends a single request from the catalogue of known commands
+	///
+	/// Fails with [`ErrorId::Unsupported`](crate::ErrorId::Unsupported) if
+	/// the X52 doesn't have the [capability](X52VendorCommand::capability)
+	/// the command needs. Unlike the other methods, this does not check that
+	/// its arguments make sense.
+	pub fn send_vendor_command(
+		&self,
+		command: &X52VendorCommand,
+	) -> Result<(), Error> {
+		self.ensure_supported(command.capability())?;
+		let (index, value) = command.encode();
+		self.send_requests(format_args!("send {command:?}"), &[(index, value)])
+	}
+
+	/// Sends an arbitrary vendor request to the X52
+	///
+	/// <div class="warning">Nothing is checked before the request is sent.
+	/// Requests outside of <code>X52VendorCommand</code> are undocumented,
+	/// and may do anything from nothing at all to leaving the X52 in a state
+	/// which only power cycling it fixes. Use at your own risk.</div>
+	///
+	/// Only available with the `raw-commands` feature, so that it has to be
+	/// asked for. The request still goes through the retry policy, and is
+	/// logged with the `log` feature.
+	#[cfg(feature = "raw-commands")]
+	pub fn send_raw_vendor_command(
+		&self,
+		index: u16,
+		value: u16,
+	) -> Result<(), Error> {
+		self.send_requests(
+			format_args!("send raw request {index:#04X} {value:#06X}"),
+			&[(index, value)],
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MockTransport, X52DeviceType};
+
+	#[test]
+	fn commands_are_encoded() {
+		let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		driver
+			.send_vendor_command(&X52VendorCommand::SetBlinkStatus(true))
+			.unwrap();
+		assert_eq!(driver.transport().requests(), vec![(0xB4, 0x51)]);
+	}
+
+	#[test]
+	#[cfg(feature = "raw-commands")]
+	fn raw_commands_are_sent_as_is() {
+		let driver = X52Driver::new(MockTransport::new(), &X52DeviceType::X52);
+		driver.send_raw_vendor_command(0x1234, 0x5678).unwrap();
+		driver
+			.send_vendor_command(&X52VendorCommand::SetBlinkStatus(true))
+			.unwrap();
+		assert_eq!(
+			driver.transport().requests(),
+			vec![(0x1234, 0x5678), (0xB4, 0x51)]
+		);
+	}
+}